@@ -1,20 +1,9 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 use cherris::{engine::Engine, *};
 
-fn count_moves_for_depth(depth: u8) -> usize {
+fn count_moves_for_depth(depth: u8) -> u64 {
     let mut game = Game::init();
-    if depth == 0 {
-        return 1;
-    }
-    let mut count = 0;
-
-    let moves = game.get_all_moves_for_color(game.white_turn);
-    for mv in moves {
-        game.play_move_ob(mv);
-        count += count_moves_for_depth(depth - 1);
-        game.undo_move();
-    }
-    count
+    game.perft(depth)
 }
 
 fn bench_best_move_gen(depth: u8) {
@@ -23,6 +12,55 @@ fn bench_best_move_gen(depth: u8) {
     let _best_move = engine.get_best_move(depth);
 }
 
+const KIWIPETE_FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn bench_move_generation(c: &mut Criterion) {
+    let mut game = Game::init();
+    game.try_set_from_fen(KIWIPETE_FEN).unwrap();
+
+    // Throughput is reported in generated moves per second
+    let mut group = c.benchmark_group("Kiwipete move generation");
+    let pseudolegal_moves = game.get_all_moves_for_color(true).len() as u64;
+    group.throughput(Throughput::Elements(pseudolegal_moves));
+    group.bench_function("pseudolegal", |b| {
+        b.iter(|| black_box(&game).get_all_moves_for_color(black_box(true)))
+    });
+    let legal_moves = game.get_legal_moves(true).len() as u64;
+    group.throughput(Throughput::Elements(legal_moves));
+    group.bench_function("legal", |b| {
+        b.iter(|| black_box(&game).get_legal_moves(black_box(true)))
+    });
+    group.finish();
+}
+
+const IMPORT_FENS: [&str; 3] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    KIWIPETE_FEN,
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+];
+
+fn bench_fen_import(c: &mut Criterion) {
+    // The array import fills the board state and then rebuilds the bitboards from it
+    let mut group = c.benchmark_group("FEN import");
+    group.throughput(Throughput::Elements(IMPORT_FENS.len() as u64));
+    let mut game = Game::init();
+    group.bench_function("array", |b| {
+        b.iter(|| {
+            for fen in IMPORT_FENS {
+                game.try_set_from_fen(black_box(fen)).unwrap();
+            }
+        })
+    });
+    group.bench_function("direct bitboards", |b| {
+        b.iter(|| {
+            for fen in IMPORT_FENS {
+                game.set_from_fen_direct(black_box(fen)).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("Move gen depth 2", |b| b.iter(|| count_moves_for_depth(black_box(2))));
     c.bench_function("Move gen depth 3", |b| b.iter(|| count_moves_for_depth(black_box(3))));
@@ -31,5 +69,5 @@ fn criterion_benchmark(c: &mut Criterion) {
 
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, criterion_benchmark, bench_move_generation, bench_fen_import);
 criterion_main!(benches);
\ No newline at end of file
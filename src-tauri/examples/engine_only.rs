@@ -0,0 +1,22 @@
+//! Uses cherris as a plain engine library, without the Tauri GUI stack.
+//!
+//! Run with `cargo run --no-default-features --example engine_only`.
+
+use cherris::engine::Engine;
+use cherris::{ChessGame, GameStatus};
+
+fn main() {
+    let mut engine = Engine::init();
+    engine.set_search_depth(1);
+
+    // Let the engine play a few moves against itself
+    for _ in 0..6 {
+        if engine.game.status() != GameStatus::Ongoing {
+            break;
+        }
+        let best_move = engine.get_best_move_default();
+        println!("{}", engine.game.move_to_san(best_move));
+        engine.game.play_move_ob(best_move);
+    }
+    println!("{}", engine.game);
+}
@@ -0,0 +1,146 @@
+//! Plays against the engine in the terminal.
+//!
+//! Moves are entered in SAN (`Nf3`) or UCI (`g1f3`) notation, and the commands `undo`, `fen`,
+//! `eval` and `quit` are also understood. The human plays the side to move in the starting
+//! position, which can be set with `--fen "<FEN>"`. `--depth <N>` sets the engine's search depth,
+//! and `--verbose` prints a summary of each of its searches.
+
+use std::io::{self, BufRead, Write};
+
+use cherris::engine::Engine;
+use cherris::{ChessGame, Game, GameStatus, Move};
+
+fn main() {
+    let mut engine = Engine::init();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--verbose" {
+            engine.set_verbose(true);
+            continue;
+        }
+        match (arg.as_str(), args.next()) {
+            ("--fen", Some(fen)) => {
+                if let Err(error) = engine.game.try_set_from_fen(&fen) {
+                    eprintln!("Invalid FEN: {}", error);
+                    return;
+                }
+            }
+            ("--depth", Some(depth)) => match depth.parse() {
+                Ok(depth) => engine.set_search_depth(depth),
+                Err(_) => {
+                    eprintln!("Invalid depth: {}", depth);
+                    return;
+                }
+            },
+            _ => {
+                eprintln!("Usage: play [--fen <FEN>] [--depth <N>] [--verbose]");
+                return;
+            }
+        }
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("{}", engine.game);
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+
+        let mv = match line.trim() {
+            "" => continue,
+            "quit" => break,
+            "fen" => {
+                println!("{}", engine.game.get_fen());
+                continue;
+            }
+            "eval" => {
+                // The terms of the static evaluation, from white's point of view
+                println!("{}", engine.evaluate_verbose(&engine.game.board));
+                continue;
+            }
+            "undo" => {
+                // Take back the engine's reply together with our own move
+                if engine.game.undo_moves(2) == 0 {
+                    println!("Nothing to undo");
+                }
+                println!("{}", engine.game);
+                continue;
+            }
+            input => match parse_move(&engine.game, input) {
+                Some(mv) => mv,
+                None => {
+                    println!("Illegal move: {}", input);
+                    continue;
+                }
+            },
+        };
+
+        engine.game.play_move_ob(mv);
+        if announce(&engine.game) {
+            break;
+        }
+
+        let reply = engine.get_best_move_default();
+        println!("Engine plays {}", engine.game.move_to_san(reply));
+        engine.game.play_move_ob(reply);
+        println!("{}", engine.game);
+        if announce(&engine.game) {
+            break;
+        }
+    }
+}
+
+/// Reads a legal move in SAN or UCI notation.
+fn parse_move(game: &Game, input: &str) -> Option<Move> {
+    if let Some(mv) = Move::from_uci(input) {
+        return game
+            .legal_moves_for(game.turn())
+            .into_iter()
+            .find(|legal_move| *legal_move == mv);
+    }
+    game.san_to_move(input)
+}
+
+/// Announces check and the end of the game. Returns true if the game is over.
+fn announce(game: &Game) -> bool {
+    match game.status() {
+        GameStatus::Ongoing => {
+            if game.is_in_check() {
+                println!("Check");
+            }
+            false
+        }
+        GameStatus::Checkmate => {
+            let winner = if game.white_turn { "Black" } else { "White" };
+            println!("Checkmate, {} wins", winner);
+            true
+        }
+        GameStatus::Stalemate => {
+            println!("Draw by stalemate");
+            true
+        }
+        GameStatus::InsufficientMaterial => {
+            println!("Draw by insufficient material");
+            true
+        }
+        GameStatus::DeadPosition => {
+            println!("Draw, no legal moves can lead to checkmate");
+            true
+        }
+        GameStatus::SeventyFiveMoveRule => {
+            println!("Draw by the 75-move rule");
+            true
+        }
+        GameStatus::FivefoldRepetition => {
+            println!("Draw by fivefold repetition");
+            true
+        }
+        GameStatus::MoveLimit => {
+            println!("Draw by the move limit");
+            true
+        }
+    }
+}
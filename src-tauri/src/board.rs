@@ -1,6 +1,34 @@
-use crate::{BISHOP, KING, KNIGHT, PAWN_BIT, PIECE_BIT, QUEEN, ROOK, WHITE_BIT};
+use crate::constants::TOTAL_PHASE;
+use crate::{KING, PIECE_BIT, WHITE_BIT};
 
-use crate::piece::{BasicPiece, Piece};
+use crate::piece::{BasicPiece, Piece, PieceType};
+
+/// Number of Zobrist keys: one per piece kind on each square, one per castling rights
+/// mask, one per en passant file and one for black to move.
+const ZOBRIST_KEY_COUNT: usize = 12 * 64 + 16 + 8 + 1;
+const ZOBRIST_CASTLING: usize = 12 * 64;
+const ZOBRIST_EN_PASSANT: usize = ZOBRIST_CASTLING + 16;
+const ZOBRIST_BLACK_TO_MOVE: usize = ZOBRIST_EN_PASSANT + 8;
+
+/// Fixed pseudo-random Zobrist keys, generated with splitmix64 so hashes are the same
+/// from one run to the next and can be shared with other tools.
+const ZOBRIST_KEYS: [u64; ZOBRIST_KEY_COUNT] = {
+    let mut keys = [0u64; ZOBRIST_KEY_COUNT];
+    let mut seed = 0x9E3779B97F4A7C15u64;
+    let mut i = 0;
+    while i < ZOBRIST_KEY_COUNT {
+        seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+};
+
+/// A square that changed between two boards, with the piece on it before and after.
+pub type SquareChange = (u8, Option<(PieceType, bool)>, Option<(PieceType, bool)>);
 
 #[derive(Debug, Clone, Hash)]
 /// Represents a chess board.
@@ -27,6 +55,10 @@ pub struct Board {
     /// - Bit 1 (2) represents black kingside castling (k)
     /// - Bit 0 (1) represents black queenside castling (q)
     pub castling: u8,
+
+    /// The material of each side in centipawns, kings excluded.
+    /// Index 0 holds the white total and index 1 the black total.
+    pub material: [i32; 2],
 }
 
 /// Represents a chess board.
@@ -38,30 +70,36 @@ impl Board {
     ///
     /// This method prints the current state of the chess board to the console.
     pub fn show(&self) {
-        println!("  |----|----|----|----|----|----|----|----|");
-        let mut row_count = 8;
+        print!("{}", self.render());
+    }
+
+    /// Renders the chess board as text.
+    ///
+    /// This method returns the same grid that `show` prints, one rank per line.
+    pub fn render(&self) -> String {
+        let separator = "  |----|----|----|----|----|----|----|----|\n";
+        let mut output = String::from(separator);
         for row in 0..8 {
-            print!("{} ", row_count);
-            row_count -= 1;
-            print!("|");
+            output.push_str(&format!("{} |", 8 - row));
 
             for col in 0..8 {
-                print!(" ");
+                output.push(' ');
 
                 // Piece print
                 if self.state[row * 8 + col] == 0u8 {
-                    print!("  ");
+                    output.push_str("  ");
                 } else {
                     let piece = Piece::init_from_binary(self.state[row * 8 + col]);
-                    print!("{}", piece.text_repr());
+                    output.push_str(&piece.text_repr());
                 }
 
-                print!(" |");
+                output.push_str(" |");
             }
-            println!();
-            println!("  |----|----|----|----|----|----|----|----|");
+            output.push('\n');
+            output.push_str(separator);
         }
-        println!("    a    b    c    d    e    f    g    h  ");
+        output.push_str("    a    b    c    d    e    f    g    h  \n");
+        output
     }
 
     /// Gets the castling FEN string.
@@ -102,7 +140,78 @@ impl Board {
             hash_value: hash,
             en_passant,
             castling,
+            material: [0i32; 2],
+        }
+    }
+
+    /// Creates an empty board with no castling rights, ready to have pieces placed on it.
+    ///
+    /// Unlike `init`, which keeps all castling rights for the start position to fill in, this
+    /// is meant for building arbitrary positions with `place` and `clear`.
+    pub fn empty() -> Self {
+        let mut board = Board::init();
+        board.castling = 0;
+        board
+    }
+
+    /// Places a piece on a square, replacing whatever was there, and keeps the bitboards
+    /// and material totals in step. Returns the board so placements can be chained.
+    pub fn place(&mut self, square: u8, piece_type: PieceType, white: bool) -> &mut Self {
+        self.clear(square);
+        let piece = piece_type.to_binary(white);
+        self.state[square as usize] = piece;
+        self.bitboard[Board::bitboard_index(piece)] |= 1u64 << Board::bit_to_square(square);
+        self.add_material(piece);
+        self
+    }
+
+    /// Removes the piece on a square, if any, keeping the bitboards and material totals in
+    /// step. Returns the board so calls can be chained.
+    pub fn clear(&mut self, square: u8) -> &mut Self {
+        let piece = self.state[square as usize];
+        if piece != 0u8 {
+            self.bitboard[Board::bitboard_index(piece)] &= !(1u64 << Board::bit_to_square(square));
+            self.remove_material(piece);
+            self.state[square as usize] = 0u8;
+        }
+        self
+    }
+
+    /// Sets the pieces from the piece placement field of a FEN record, filling the board
+    /// state, bitboards and material totals in the same pass rather than rebuilding the
+    /// bitboards afterwards. Castling rights and en passant are left alone.
+    /// Returns false, with the board emptied, if the placement does not cover 64 squares.
+    pub fn set_pieces_from_fen(&mut self, placement: &str) -> bool {
+        self.state = [0u8; 64];
+        self.bitboard = [0u64; 12];
+        self.material = [0i32; 2];
+
+        let mut square = 0u8;
+        for c in placement.chars() {
+            if c == '/' {
+                continue;
+            }
+            if let Some(empty) = c.to_digit(10) {
+                square += empty as u8;
+            } else if let (Some(piece_type), true) = (PieceType::from_char(c), square < 64) {
+                let piece = piece_type.to_binary(c.is_uppercase());
+                self.state[square as usize] = piece;
+                self.bitboard[Board::bitboard_index(piece)] |= 1u64 << Board::bit_to_square(square);
+                self.add_material(piece);
+                square += 1;
+            } else {
+                square = u8::MAX;
+                break;
+            }
+        }
+
+        if square != 64 {
+            self.state = [0u8; 64];
+            self.bitboard = [0u64; 12];
+            self.material = [0i32; 2];
+            return false;
         }
+        true
     }
 
     /// Gets the position of the king.
@@ -116,7 +225,7 @@ impl Board {
     /// # Returns
     ///
     /// The position of the king as a u8 value. If the king is not found, 65 is returned.
-    //TODO: change this to return an Option
+    /// Prefer `king_square`, which returns an `Option` instead of a sentinel value.
     pub fn get_king_position(&self, is_white: bool) -> u8 {
         let king_byte = if is_white {
             PIECE_BIT + WHITE_BIT + KING
@@ -132,6 +241,267 @@ impl Board {
         65
     }
 
+    /// Gets the position of the king from the bitboards.
+    ///
+    /// This is a constant-time alternative to `get_king_position` that reads the king
+    /// bitboard (index 5 for white, 11 for black) instead of scanning the board state.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_white` - A boolean indicating whether the king is white or black.
+    ///
+    /// # Returns
+    ///
+    /// The position of the king, or `None` if there is no king of that color on the board.
+    pub fn king_square(&self, is_white: bool) -> Option<u8> {
+        let king_bitboard = if is_white {
+            self.bitboard[5]
+        } else {
+            self.bitboard[11]
+        };
+        if king_bitboard == 0 {
+            return None;
+        }
+        Some(Board::bit_to_square(king_bitboard.trailing_zeros() as u8))
+    }
+
+    /// Rebuilds the bitboards from the board state.
+    ///
+    /// The bitboards are indexed with a1 as bit 0 and h8 as bit 63, which is the
+    /// board state index with the rank mirrored.
+    pub fn sync_bitboards(&mut self) {
+        self.bitboard = [0u64; 12];
+        for square in 0..64u8 {
+            let piece = self.state[square as usize];
+            if piece == 0u8 {
+                continue;
+            }
+            let index = Board::bitboard_index(piece);
+            self.bitboard[index] |= 1u64 << Board::bit_to_square(square);
+        }
+    }
+
+    /// Returns the Zobrist hash of the pieces, castling rights and en passant square.
+    ///
+    /// The side to move is not part of the board, `Game::hash` adds it on top.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0u64;
+        for square in 0..64usize {
+            let piece = self.state[square];
+            if piece != 0u8 {
+                hash ^= ZOBRIST_KEYS[Board::bitboard_index(piece) * 64 + square];
+            }
+        }
+        hash ^= ZOBRIST_KEYS[ZOBRIST_CASTLING + (self.castling & 0b1111) as usize];
+        if self.en_passant != 0 {
+            let file = (self.en_passant % 8) as usize;
+            hash ^= ZOBRIST_KEYS[ZOBRIST_EN_PASSANT + file];
+        }
+        hash
+    }
+
+    /// Returns a Zobrist hash of the pawns alone, using the same keys as `zobrist_hash`.
+    /// Boards with the same pawns share it whatever the other pieces, castling rights and
+    /// en passant square, so it keys evaluation terms that only depend on the pawns.
+    pub fn pawn_hash(&self) -> u64 {
+        let mut hash = 0u64;
+        for index in [0usize, 6] {
+            let mut pawns = self.bitboard[index];
+            while pawns != 0 {
+                let square = (pawns.trailing_zeros() ^ 56) as usize;
+                pawns &= pawns - 1;
+                hash ^= ZOBRIST_KEYS[index * 64 + square];
+            }
+        }
+        hash
+    }
+
+    /// Returns the Zobrist key toggled in or out when it is black's turn to move.
+    pub fn zobrist_black_to_move() -> u64 {
+        ZOBRIST_KEYS[ZOBRIST_BLACK_TO_MOVE]
+    }
+
+    /// Returns the bitboard that holds the given piece: pawns, rooks, knights, bishops,
+    /// queens and king in that order, white first.
+    fn bitboard_index(piece: u8) -> usize {
+        let piece = Piece::init_from_binary(piece);
+        let index = match piece.class {
+            PieceType::Pawn => 0,
+            PieceType::Rook => 1,
+            PieceType::Knight => 2,
+            PieceType::Bishop => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+        };
+        if piece.is_white {
+            index
+        } else {
+            index + 6
+        }
+    }
+
+    /// Recomputes the material totals from the board state.
+    ///
+    /// Moves keep these up to date incrementally, so this is only needed after the board
+    /// state is written directly, such as when setting up a position.
+    pub fn recompute_material(&mut self) {
+        self.material = [0i32; 2];
+        for square in 0..64 {
+            let piece = self.state[square];
+            if piece != 0u8 {
+                self.add_material(piece);
+            }
+        }
+    }
+
+    /// Adds a piece to the material totals.
+    pub fn add_material(&mut self, piece: u8) {
+        let piece = Piece::init_from_binary(piece);
+        if piece.class == PieceType::King {
+            return;
+        }
+        let side = if piece.is_white { 0 } else { 1 };
+        self.material[side] += piece.class.value();
+    }
+
+    /// Removes a piece from the material totals.
+    pub fn remove_material(&mut self, piece: u8) {
+        let piece = Piece::init_from_binary(piece);
+        if piece.class == PieceType::King {
+            return;
+        }
+        let side = if piece.is_white { 0 } else { 1 };
+        self.material[side] -= piece.class.value();
+    }
+
+    /// Returns the game phase counted from the pieces on the bitboards, from `TOTAL_PHASE`
+    /// with every minor and major piece on the board down to 0 when only kings and pawns
+    /// are left. Extra pieces from promotions cannot push it above `TOTAL_PHASE`.
+    pub fn game_phase(&self) -> u8 {
+        let classes = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+        ];
+        let phase: i32 = classes
+            .iter()
+            .map(|class| {
+                let white = self.bitboard[Board::bitboard_index(class.to_binary(true))];
+                let black = self.bitboard[Board::bitboard_index(class.to_binary(false))];
+                (white.count_ones() + black.count_ones()) as i32 * class.phase_weight()
+            })
+            .sum();
+        phase.clamp(0, TOTAL_PHASE) as u8
+    }
+
+    /// Converts between bitboard bit indices and board state indices.
+    /// The mapping mirrors the rank, so it is its own inverse.
+    #[inline]
+    fn bit_to_square(index: u8) -> u8 {
+        index ^ 56
+    }
+
+    /// Checks whether a square is empty.
+    ///
+    /// Returns `false` for squares outside the board.
+    #[inline]
+    pub fn is_empty(&self, square: u8) -> bool {
+        self.state.get(square as usize).is_some_and(|x| *x == 0u8)
+    }
+
+    /// Gets the color of the piece on a square.
+    ///
+    /// Returns `Some(true)` for a white piece, `Some(false)` for a black piece and `None`
+    /// if the square is empty or outside the board.
+    #[inline]
+    pub fn piece_color(&self, square: u8) -> Option<bool> {
+        match self.state.get(square as usize) {
+            Some(&piece) if piece != 0u8 => Some(piece & WHITE_BIT == WHITE_BIT),
+            _ => None,
+        }
+    }
+
+    /// Gets the type and color of the piece on a square, the color being `true` for white.
+    ///
+    /// Returns `None` if the square is empty or outside the board.
+    pub fn piece_on(&self, square: u8) -> Option<(PieceType, bool)> {
+        self.piece_at(square)
+            .map(|piece| (piece.class, piece.is_white))
+    }
+
+    /// Gets the piece on a square.
+    ///
+    /// Returns `None` if the square is empty, outside the board or holds a byte that does
+    /// not decode to a piece, so a corrupted board state cannot cause a panic.
+    pub fn piece_at(&self, square: u8) -> Option<Piece> {
+        Piece::try_from_binary(*self.state.get(square as usize)?)
+    }
+
+    /// Returns a bitboard of the pieces of the given color that attack `square`, whether or
+    /// not the square is occupied. Bits follow the layout of `bitboard`, so the result can be
+    /// masked with a piece's bitboard, e.g. `attackers_of(sq, true) & bitboard[0]` for the
+    /// white pawns attacking it.
+    pub fn attackers_of(&self, square: u8, by_white: bool) -> u64 {
+        let mut attackers = 0u64;
+        for source in 0..64u8 {
+            if self.piece_color(source) != Some(by_white) {
+                continue;
+            }
+            let piece = Piece::init_from_binary(self.state[source as usize]);
+            if piece.attacked_squares(source, self).contains(&square) {
+                attackers |= 1u64 << (source ^ 56);
+            }
+        }
+        attackers
+    }
+
+    /// Lists the squares whose contents differ between this board and `other`, in square
+    /// order, each with the piece standing there before (on `self`) and after (on `other`).
+    /// Castling reports both the king and the rook, and en passant the vanished pawn.
+    pub fn diff(&self, other: &Board) -> Vec<SquareChange> {
+        (0..64u8)
+            .filter(|&square| self.state[square as usize] != other.state[square as usize])
+            .map(|square| (square, self.piece_on(square), other.piece_on(square)))
+            .collect()
+    }
+
+    /// Checks whether a square holds a piece of the opposite color to `my_white`.
+    #[inline]
+    pub fn is_enemy(&self, square: u8, my_white: bool) -> bool {
+        self.piece_color(square)
+            .is_some_and(|is_white| is_white != my_white)
+    }
+
+    /// Returns the board flipped vertically with the piece colors swapped.
+    ///
+    /// Rank 1 becomes rank 8, white pieces become black pieces and vice versa, and the
+    /// castling rights and en passant square are mirrored accordingly. The mirrored board
+    /// is the same position seen from the other side, which is useful to check that the
+    /// evaluation has no built-in color bias.
+    pub fn mirror(&self) -> Board {
+        let mut mirrored = Board::init();
+
+        for square in 0..64 {
+            let piece = self.state[square];
+            if piece != 0u8 {
+                mirrored.state[square ^ 56] = piece ^ WHITE_BIT;
+            }
+        }
+
+        // White bitboards (0-5) swap places with black bitboards (6-11)
+        for i in 0..12 {
+            mirrored.bitboard[i] = self.bitboard[(i + 6) % 12].swap_bytes();
+        }
+
+        mirrored.castling = ((self.castling & 0b1100) >> 2) | ((self.castling & 0b0011) << 2);
+        if self.en_passant != 0 {
+            mirrored.en_passant = self.en_passant ^ 56;
+        }
+        mirrored.material = [self.material[1], self.material[0]];
+        mirrored
+    }
+
     /// Sets the start position of the chess board.
     ///
     /// This method sets the chess board to the standard starting position.
@@ -180,23 +550,15 @@ impl Board {
                 board_state_index += num;
             } else {
                 // Set the piece
-                let mut piece = PIECE_BIT;
-                if c.is_uppercase() {
-                    piece += WHITE_BIT;
-                }
-                match c {
-                    'p' | 'P' => piece += PAWN_BIT,
-                    'r' | 'R' => piece += ROOK,
-                    'n' | 'N' => piece += KNIGHT,
-                    'b' | 'B' => piece += BISHOP,
-                    'q' | 'Q' => piece += QUEEN,
-                    'k' | 'K' => piece += KING,
-                    _ => panic!("This piece does not exist!"),
-                }
+                let Some(piece_type) = PieceType::from_char(c) else {
+                    panic!("This piece does not exist!");
+                };
+                let piece = piece_type.to_binary(c.is_uppercase());
                 let index: usize = board_state_index.try_into().unwrap();
                 self.state[index] = piece;
                 board_state_index += 1;
             }
         }
+        self.recompute_material();
     }
 }
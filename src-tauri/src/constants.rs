@@ -9,3 +9,17 @@ pub const KNIGHT: u8 = 4u8;
 pub const ROOK: u8 = 6u8;
 pub const ROW: u8 = 8u8;
 pub const COL: u8 = 1u8;
+
+// Move flags
+pub const CAPTURE_FLAG: u8 = 1u8;
+pub const EN_PASSANT_FLAG: u8 = 2u8;
+pub const CASTLE_FLAG: u8 = 4u8;
+pub const DOUBLE_PUSH_FLAG: u8 = 8u8;
+pub const PROMOTION_FLAG: u8 = 16u8;
+
+// Game phase
+pub const TOTAL_PHASE: i32 = 24i32;
+
+// Draw rules, in half moves without a capture or pawn move
+pub const FIFTY_MOVE_RULE: i32 = 100i32;
+pub const SEVENTY_FIVE_MOVE_RULE: i32 = 150i32;
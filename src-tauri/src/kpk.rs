@@ -0,0 +1,229 @@
+//! King and pawn against king oracle.
+//!
+//! Every position with a white king, a white pawn and a black king is solved once by
+//! retrograde analysis the first time it is needed. Squares use the board's indexing,
+//! 0 is a8 and 63 is h1, so the white pawn moves towards lower indices.
+
+use std::sync::OnceLock;
+
+/// Both sides to move, 64 black king squares, 64 white king squares and 24 pawn squares
+/// (files a to d, ranks 2 to 7). Positions with the pawn on files e to h are mirrored.
+const POSITION_COUNT: usize = 2 * 64 * 64 * 24;
+
+const KING_STEPS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+/// A position of the table, with the pawn already on files a to d.
+#[derive(Clone, Copy)]
+struct Position {
+    white_to_move: bool,
+    wk: u8,
+    bk: u8,
+    wp: u8,
+}
+
+impl Position {
+    fn index(&self) -> usize {
+        let pawn = (self.wp / 8 - 1) as usize * 4 + (self.wp % 8) as usize;
+        ((self.white_to_move as usize * 64 + self.bk as usize) * 64 + self.wk as usize) * 24 + pawn
+    }
+
+    fn from_index(index: usize) -> Position {
+        let pawn = index % 24;
+        Position {
+            white_to_move: index / (24 * 64 * 64) == 1,
+            wk: (index / 24 % 64) as u8,
+            bk: (index / (24 * 64) % 64) as u8,
+            wp: ((pawn / 4 + 1) * 8 + pawn % 4) as u8,
+        }
+    }
+}
+
+fn distance(a: u8, b: u8) -> u8 {
+    let rows = (a / 8).abs_diff(b / 8);
+    let cols = (a % 8).abs_diff(b % 8);
+    rows.max(cols)
+}
+
+fn pawn_attacks(wp: u8, square: u8) -> bool {
+    square / 8 + 1 == wp / 8 && (square % 8).abs_diff(wp % 8) == 1
+}
+
+fn king_steps(square: u8) -> impl Iterator<Item = u8> {
+    let (row, col) = ((square / 8) as i8, (square % 8) as i8);
+    KING_STEPS.iter().filter_map(move |(dr, dc)| {
+        let (r, c) = (row + dr, col + dc);
+        ((0..8).contains(&r) && (0..8).contains(&c)).then_some((r * 8 + c) as u8)
+    })
+}
+
+/// Sets the outcomes that do not depend on other positions: illegal placements, immediate
+/// safe promotions, stalemates and the black king taking an undefended pawn.
+fn initial_outcome(pos: Position) -> Outcome {
+    let Position {
+        white_to_move,
+        wk,
+        bk,
+        wp,
+    } = pos;
+    if wk == bk || wk == wp || bk == wp || distance(wk, bk) <= 1 {
+        return Outcome::Invalid;
+    }
+    if white_to_move {
+        // Black cannot be in check with white to move
+        if pawn_attacks(wp, bk) {
+            return Outcome::Invalid;
+        }
+        // The pawn queens and the queen cannot be taken
+        let queening = wp - 8;
+        if wp / 8 == 1
+            && queening != wk
+            && queening != bk
+            && (distance(bk, queening) > 1 || distance(wk, queening) == 1)
+        {
+            return Outcome::Win;
+        }
+    } else {
+        if distance(bk, wp) == 1 && distance(wk, wp) > 1 {
+            return Outcome::Draw;
+        }
+        let has_move =
+            king_steps(bk).any(|to| to != wp && distance(to, wk) > 1 && !pawn_attacks(wp, to));
+        if !has_move {
+            return Outcome::Draw;
+        }
+    }
+    Outcome::Unknown
+}
+
+/// Works out an unknown position from the positions it leads to, or returns `Unknown`
+/// while some of them are still unsolved.
+fn classify(pos: Position, outcomes: &[Outcome]) -> Outcome {
+    let Position {
+        white_to_move,
+        wk,
+        bk,
+        wp,
+    } = pos;
+    let mut next = vec![];
+    if white_to_move {
+        for to in king_steps(wk).filter(|&to| to != wp && distance(to, bk) > 1) {
+            next.push(Position {
+                white_to_move: false,
+                wk: to,
+                ..pos
+            });
+        }
+        // Pushes to the last rank are already settled by the initial outcomes
+        if wp / 8 > 1 && wp - 8 != wk && wp - 8 != bk {
+            next.push(Position {
+                white_to_move: false,
+                wp: wp - 8,
+                ..pos
+            });
+            if wp / 8 == 6 && wp - 16 != wk && wp - 16 != bk {
+                next.push(Position {
+                    white_to_move: false,
+                    wp: wp - 16,
+                    ..pos
+                });
+            }
+        }
+    } else {
+        for to in
+            king_steps(bk).filter(|&to| to != wp && distance(to, wk) > 1 && !pawn_attacks(wp, to))
+        {
+            next.push(Position {
+                white_to_move: true,
+                bk: to,
+                ..pos
+            });
+        }
+    }
+
+    let (good, bad) = if white_to_move {
+        (Outcome::Win, Outcome::Draw)
+    } else {
+        (Outcome::Draw, Outcome::Win)
+    };
+    let next: Vec<Outcome> = next.iter().map(|p| outcomes[p.index()]).collect();
+    if next.contains(&good) {
+        good
+    } else if next.iter().all(|&outcome| outcome == bad) {
+        bad
+    } else {
+        Outcome::Unknown
+    }
+}
+
+/// Solves every position and keeps one bit per position, set when white wins.
+fn solve() -> Vec<u64> {
+    let mut outcomes: Vec<Outcome> = (0..POSITION_COUNT)
+        .map(|index| initial_outcome(Position::from_index(index)))
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..POSITION_COUNT {
+            if outcomes[index] != Outcome::Unknown {
+                continue;
+            }
+            let outcome = classify(Position::from_index(index), &outcomes);
+            if outcome != Outcome::Unknown {
+                outcomes[index] = outcome;
+                changed = true;
+            }
+        }
+    }
+
+    // Whatever is left unknown is a position white cannot force anything from
+    let mut wins = vec![0u64; POSITION_COUNT.div_ceil(64)];
+    for (index, outcome) in outcomes.iter().enumerate() {
+        if *outcome == Outcome::Win {
+            wins[index / 64] |= 1 << (index % 64);
+        }
+    }
+    wins
+}
+
+/// Returns true if white wins with perfect play, with the white king on `wk`, the black
+/// king on `bk` and the white pawn on `wp`. Positions that cannot arise, such as the pawn
+/// on the first or last rank or the kings touching, count as not won.
+pub fn kpk_is_won(white_to_move: bool, wk: u8, bk: u8, wp: u8) -> bool {
+    static TABLE: OnceLock<Vec<u64>> = OnceLock::new();
+    if wk > 63 || bk > 63 || !(8..56).contains(&wp) {
+        return false;
+    }
+    // The table only holds the pawn on files a to d
+    let (wk, bk, wp) = if wp % 8 > 3 {
+        (wk ^ 7, bk ^ 7, wp ^ 7)
+    } else {
+        (wk, bk, wp)
+    };
+    let index = Position {
+        white_to_move,
+        wk,
+        bk,
+        wp,
+    }
+    .index();
+    let table = TABLE.get_or_init(solve);
+    table[index / 64] & (1 << (index % 64)) != 0
+}
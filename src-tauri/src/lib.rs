@@ -1,11 +1,19 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::vec;
 
+use rand::Rng;
+use rayon::prelude::*;
+
 pub mod board;
 pub mod constants;
+pub mod kpk;
 pub mod piece;
 
 use crate::constants::{
-    BISHOP, CHECK_PIECE, COL, KING, KNIGHT, PAWN_BIT, PIECE_BIT, QUEEN, ROOK, ROW, WHITE_BIT,
+    CAPTURE_FLAG, CASTLE_FLAG, CHECK_PIECE, COL, DOUBLE_PUSH_FLAG, EN_PASSANT_FLAG,
+    FIFTY_MOVE_RULE, KING, PIECE_BIT, PROMOTION_FLAG, QUEEN, ROW, SEVENTY_FIVE_MOVE_RULE,
+    WHITE_BIT,
 };
 use board::Board;
 use piece::{BasicPiece, Piece, PieceType};
@@ -31,6 +39,15 @@ pub struct Game {
 
 pub trait ChessGame {
     fn remove_illegal_moves(&self, moves: Vec<Move>) -> Vec<Move>;
+    /// Plays the move between two square names, such as "e2" and "e4", promoting to a
+    /// queen for "Q" or "q". Returns false if the move could not be played.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either square is not a square name; see `Game::try_play_move`.
+    #[deprecated(
+        note = "use `Game::try_play_move`, which reports bad squares instead of panicking"
+    )]
     fn play_move_from_string(
         &mut self,
         initial_position: &str,
@@ -41,13 +58,23 @@ pub trait ChessGame {
     fn play_move_ob(&mut self, chess_move: Move) -> bool;
     fn get_fen(&self) -> String;
     fn set_from_simple_fen(&mut self, fen: String) -> bool;
+    /// Sets the position from a FEN record.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the record is malformed; see `Game::try_set_from_fen`.
+    #[deprecated(
+        note = "use `Game::try_set_from_fen`, which reports a malformed FEN instead of panicking"
+    )]
     fn set_from_fen(&mut self, fen: String);
     fn get_fen_simple(&self) -> String;
     fn restart(&mut self);
-    fn undo_move(&mut self);
+    fn undo_move(&mut self) -> bool;
     fn get_pseudolegal_moves(&self, position: String) -> Vec<String>;
+    fn get_pseudolegal_moves_from(&self, square: u8) -> Vec<Move>;
     fn get_all_moves_for_color(&self, white: bool) -> Vec<Move>;
     fn get_capture_moves(&self) -> Vec<Move>;
+    fn get_quiet_moves(&self) -> Vec<Move>;
     fn get_legal_moves(&self, white: bool) -> Vec<Move>;
     // fn play_legal_move(&mut self, mv: &Move) -> bool;
 }
@@ -58,13 +85,13 @@ pub trait ChessDebugInfo {
 
 impl ChessDebugInfo for Game {
     fn get_piece_at_square(&self, square: String) -> String {
-        let index = position_helper::letter_to_index(square);
-        let piece_byte = self.board.state.get(index as usize);
-        if let Some(piece_byte) = piece_byte {
-            let piece = Piece::init_from_binary(*piece_byte);
-            return piece.fen_repr();
+        let Ok(index) = position_helper::try_letter_to_index(&square) else {
+            return String::from("None");
+        };
+        match self.board.piece_at(index) {
+            Some(piece) => piece.fen_repr(),
+            None => String::from("None"),
         }
-        String::from("None")
     }
 }
 
@@ -86,288 +113,243 @@ impl Game {
     pub fn show(&self) {
         self.board.show();
     }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Move {
-    pub source: u8,    // source position byte
-    pub target: u8,    // target position byte
-    pub promotion: u8, // piece to promote to
-}
-
-pub struct MoveOutput {
-    pub source: u8,
-    pub target: u8,
-    pub promotion: u8,
-    pub taken_piece: u8,
-    pub valid: bool,
-}
-
-/// Implements the `ChessGame` trait for the `Game` struct.
-/// This trait provides methods for playing chess moves, getting legal moves, removing illegal moves, and more.
-impl ChessGame for Game {
-    /// Returns a vector of legal moves for the specified color.
-    /// The `white` parameter indicates whether the moves are for the white player.
-    fn get_legal_moves(&self, is_white: bool) -> Vec<Move> {
-        // define the filter function
-        let moves = self.get_all_moves_for_color(is_white);
-        self.remove_illegal_moves(moves)
-    }
-
-    /// Removes illegal moves from the given vector of pseudolegal moves.
-    /// Returns a new vector containing only the legal moves.
-    fn remove_illegal_moves(&self, moves: Vec<Move>) -> Vec<Move> {
-        let mut game_copy = self.clone();
-        let mut final_moves: Vec<Move> = vec![];
-        let mut king_position = game_copy.board.get_king_position(game_copy.white_turn);
 
-        // No king found
-        if king_position == 65u8 {
-            let move_vec: Vec<Move> = vec![];
-            return move_vec;
+    /// Converts a move in the current position to Standard Algebraic Notation (SAN).
+    /// The move is expected to be legal for the side to move.
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let piece_bits = self.board.state[mv.source as usize];
+        if piece_bits == 0 {
+            return String::new();
         }
+        let piece = Piece::init_from_binary(piece_bits);
+        let source_square = position_helper::index_to_letter(mv.source);
+        let target_square = position_helper::index_to_letter(mv.target);
+        let source_col = position_helper::get_col(mv.source);
+        let target_col = position_helper::get_col(mv.target);
+
+        let mut san = String::new();
+        let castle_distance = mv.target as i16 - mv.source as i16;
+        if piece.class == PieceType::King && castle_distance.abs() == 2 {
+            if castle_distance > 0 {
+                san.push_str("O-O");
+            } else {
+                san.push_str("O-O-O");
+            }
+        } else {
+            // Pawns moving diagonally always capture, even en passant onto an empty square
+            let is_capture = self.board.state[mv.target as usize] != 0
+                || (piece.class == PieceType::Pawn && source_col != target_col);
 
-        let mut king_in_check;
-        for mv in moves {
-            let success = game_copy.play_move_ob(mv);
+            if piece.class == PieceType::Pawn {
+                if is_capture {
+                    san.push_str(&source_square[0..1]);
+                }
+            } else {
+                san.push_str(&piece.fen_repr().to_uppercase());
+                san.push_str(&self.san_disambiguation(mv));
+            }
 
-            // check for the original king's positions
-            king_position = game_copy.board.get_king_position(!game_copy.white_turn);
-            if !success {
-                continue;
+            if is_capture {
+                san.push('x');
             }
+            san.push_str(&target_square);
 
-            king_in_check = false;
-            let oponent_moves = game_copy.get_all_moves_for_color(game_copy.white_turn);
-            for oponent_move in oponent_moves {
-                if oponent_move.target == king_position {
-                    king_in_check = true;
-                    break;
-                }
+            if mv.promotion != 0 {
+                let promotion_piece = Piece::init_from_binary(mv.promotion);
+                san.push('=');
+                san.push_str(&promotion_piece.fen_repr().to_uppercase());
             }
-            if !king_in_check {
-                final_moves.push(mv);
+        }
+
+        // Check and checkmate suffixes are decided from the opponent's point of view
+        let mut game_copy = self.clone();
+        if game_copy.play_move_ob(mv) && game_copy.king_in_check(game_copy.white_turn) {
+            if game_copy.legal_moves_for(game_copy.turn()).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
             }
-            game_copy.undo_move();
         }
-        final_moves
-    }
 
-    /// Returns a vector of capture moves for the current player.
-    /// A capture move is a move that captures an opponent's piece.
-    fn get_capture_moves(&self) -> Vec<Move> {
-        let mut moves = self.get_all_moves_for_color(self.white_turn);
-        moves.retain(|x| self.board.state[x.target as usize] != 0);
-        moves
+        san
     }
 
-    //TODO: Optimize - use bit check instead of init_from_binary
-    /// Returns a vector of all possible moves for the specified color incluiding captures.
-    /// The `white` parameter indicates whether the moves are for the white player.
-    fn get_all_moves_for_color(&self, white_turn: bool) -> Vec<Move> {
-        let mut moves = vec![];
+    /// Finds the legal move for the side to move written in Standard Algebraic Notation (SAN).
+    /// Check and annotation suffixes are optional, and castling may be written with zeros.
+    pub fn san_to_move(&self, san: &str) -> Option<Move> {
+        let strip = |san: &str| {
+            san.trim()
+                .trim_end_matches(['+', '#', '!', '?'])
+                .replace('0', "O")
+        };
+        let san = strip(san);
+        self.legal_moves_for(self.turn())
+            .into_iter()
+            .find(|mv| strip(&self.move_to_san(*mv)) == san)
+    }
 
-        for square in 0..64 {
-            let piece = self.board.state[square];
-            if piece == 0 {
-                continue;
-            }
-            // avoid initialising the piece unless we have to get the moves
-            let is_piece_white: bool = piece & WHITE_BIT != 0;
-            if white_turn != is_piece_white {
-                continue;
+    /// Converts a principal variation into SAN strings, playing each move on a copy of the game
+    /// so that the side to move alternates. Conversion stops at the first move that cannot be played.
+    pub fn pv_to_san(&self, pv: &[Move]) -> Vec<String> {
+        let mut game_copy = self.clone();
+        let mut san_moves = Vec::with_capacity(pv.len());
+        for mv in pv {
+            let san = game_copy.move_to_san(*mv);
+            if !game_copy.play_move_ob(*mv) {
+                break;
             }
-
-            let piece = Piece::init_from_binary(piece);
-            moves.append(&mut piece.possible_moves(square as u8, &self.board));
+            san_moves.push(san);
         }
-        moves
+        san_moves
     }
 
-    /// Returns a vector of pseudolegal moves for the specified source square.
-    /// Pseudolegal moves are moves that are valid according to the rules of chess,
-    /// but may leave the king in check.
-    fn get_pseudolegal_moves(&self, source_square: String) -> Vec<String> {
-        let position_index = position_helper::letter_to_index(source_square);
-        let piece_opt = self.board.state.get(position_index as usize);
-        if piece_opt.is_none() || piece_opt.is_some_and(|x| *x == 0u8) {
-            return vec![];
+    /// Returns the file and/or rank needed to tell `mv` apart from other legal moves
+    /// of an identical piece to the same target square.
+    fn san_disambiguation(&self, mv: Move) -> String {
+        let piece_bits = self.board.state[mv.source as usize];
+        let rivals: Vec<Move> = self
+            .legal_moves_for(self.turn())
+            .into_iter()
+            .filter(|x| {
+                x.target == mv.target
+                    && x.source != mv.source
+                    && self.board.state[x.source as usize] == piece_bits
+            })
+            .collect();
+
+        if rivals.is_empty() {
+            return String::new();
         }
 
-        let piece = Piece::init_from_binary(*piece_opt.unwrap());
-        piece
-            .possible_moves(position_index, &self.board)
+        let source_square = position_helper::index_to_letter(mv.source);
+        let source_col = position_helper::get_col(mv.source);
+        let source_row = position_helper::get_row(mv.source);
+        if rivals
             .iter()
-            .map(|x| position_helper::index_to_letter(x.target))
-            .collect()
-    }
-
-    fn undo_move(&mut self) {
-        if self.previous_fen_positions.is_empty() {
-            return;
+            .all(|x| position_helper::get_col(x.source) != source_col)
+        {
+            source_square[0..1].to_string()
+        } else if rivals
+            .iter()
+            .all(|x| position_helper::get_row(x.source) != source_row)
+        {
+            source_square[1..2].to_string()
+        } else {
+            source_square
         }
-        let last_move = self.previous_fen_positions.pop().unwrap();
-        self.game_done = false;
-        self.set_from_fen(last_move);
     }
 
-    fn restart(&mut self) {
-        let mut board = Board::init();
-        board.set_start_position();
-        self.white_turn = true;
-        self.previous_fen_positions = vec![];
-        self.board = board;
-        self.game_done = false;
-        self.en_passant = "-".to_string();
-        self.half_move_clock = 0i32;
-        self.full_move_number = 1i32;
+    /// Returns true if the king of the given color is attacked by any opposing piece.
+    /// A missing king counts as being in check, since the position is already lost.
+    fn king_in_check(&self, is_white: bool) -> bool {
+        let Some(king_position) = self.board.king_square(is_white) else {
+            return true;
+        };
+        self.all_moves_for(Color::from(is_white).opposite())
+            .iter()
+            .any(|x| x.target == king_position)
     }
 
-    /// Plays the specified move by calling the `play_move` method with the move's source and target squares.
-    /// Returns `true` if the move was played successfully, `false` otherwise.
-    fn play_move_ob(&mut self, chess_move: Move) -> bool {
-        self.play_move(chess_move, false)
+    /// Returns true if `mv` is legal for the side to move.
+    /// Only the moves of the piece on the source square are generated, and the move is then
+    /// played on a copy of the game to check that it does not leave the king in check.
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        let mut game_copy = self.clone();
+        !self.castles_through_check(mv)
+            && game_copy.play_move(*mv, true)
+            && !game_copy.king_in_check(self.white_turn)
     }
 
-    fn play_move_from_string(
-        &mut self,
-        source_square: &str,
-        target_square: &str,
-        promotion_piece: &str,
-    ) -> bool {
-        let initial_position_byte = position_helper::letter_to_index(source_square.to_string());
-        let final_position_byte = position_helper::letter_to_index(target_square.to_string());
-        let _promotion = match promotion_piece {
-            "Q" => PIECE_BIT + WHITE_BIT + QUEEN,
-            "q" => PIECE_BIT + QUEEN,
-            _ => 0,
-        };
-        let mv = Move {
-            source: initial_position_byte,
-            target: final_position_byte,
-            promotion: _promotion,
+    /// Returns true if `mv` castles out of check or over a square attacked by the
+    /// opponent. Landing in check is left to the usual check after the move is played.
+    fn castles_through_check(&self, mv: &Move) -> bool {
+        let Some((PieceType::King, is_white)) = self.board.piece_on(mv.source) else {
+            return false;
         };
-        self.play_move(mv, false)
-    }
-
-    fn set_from_simple_fen(&mut self, fen: String) -> bool {
-        // Reset the board
-        self.board = Board::init();
-
-        // Set the board state
-        let mut board_state_index = 0;
-        for c in fen.chars() {
-            if c == '/' {
-                continue;
-            }
-            if c.is_numeric() {
-                let num = c.to_digit(10).unwrap();
-                board_state_index += num;
-            } else {
-                // Set the piece
-                let mut piece = PIECE_BIT;
-                if c.is_uppercase() {
-                    piece += WHITE_BIT;
-                }
-                match c {
-                    'p' | 'P' => piece += PAWN_BIT,
-                    'r' | 'R' => piece += ROOK,
-                    'n' | 'N' => piece += KNIGHT,
-                    'b' | 'B' => piece += BISHOP,
-                    'q' | 'Q' => piece += QUEEN,
-                    'k' | 'K' => piece += KING,
-                    _ => panic!("This piece does not exist!"),
-                }
-                let index: usize = board_state_index.try_into().unwrap();
-                self.board.state[index] = piece;
-                board_state_index += 1;
-            }
+        if mv.source.abs_diff(mv.target) != 2 {
+            return false;
         }
-
-        true
+        self.king_in_check(is_white) || self.is_attacked((mv.source + mv.target) / 2, !is_white)
     }
 
-    fn set_from_fen(&mut self, fen: String) {
-        // Check the size of the fen
-
-        // Reset the board
-        self.board.state = [0u8; 64];
-
-        // Split the fen
-        let mut fen_split = fen.split(' ');
-        let board_state = fen_split.next().unwrap();
-        let turn = fen_split.next().unwrap();
-        let castling_options = fen_split.next().unwrap();
-        let en_passant = fen_split.next().unwrap();
-        let half_move_clock = fen_split.next().unwrap();
-        let full_move_number = fen_split.next().unwrap();
+    /// Returns the legal moves of the side to move.
+    pub fn all_legal_moves(&self) -> Vec<Move> {
+        self.legal_moves_for(self.turn())
+    }
 
-        // Set the board state
-        let mut board_state_index = 0;
-        for c in board_state.chars() {
-            if c == '/' {
-                continue;
-            }
-            if c.is_numeric() {
-                let num = c.to_digit(10).unwrap();
-                board_state_index += num;
-            } else {
-                // Set the piece
-                let mut piece = PIECE_BIT;
-                if c.is_uppercase() {
-                    piece += WHITE_BIT;
-                }
-                match c {
-                    'p' | 'P' => piece += PAWN_BIT,
-                    'r' | 'R' => piece += ROOK,
-                    'n' | 'N' => piece += KNIGHT,
-                    'b' | 'B' => piece += BISHOP,
-                    'q' | 'Q' => piece += QUEEN,
-                    'k' | 'K' => piece += KING,
-                    _ => panic!("This piece does not exist!"),
-                }
-                let index: usize = board_state_index.try_into().unwrap();
-                self.board.state[index] = piece;
-                board_state_index += 1;
-            }
+    /// Plays `mv` on a copy of the game and returns how many legal replies the opponent
+    /// has, which is the move's count in a depth 2 perft divide. Returns 0 if the move
+    /// cannot be played.
+    pub fn legal_reply_count_after(&self, mv: &Move) -> usize {
+        let mut game_copy = self.clone();
+        if !game_copy.play_move_ob(*mv) {
+            return 0;
         }
+        game_copy.all_legal_moves().len()
+    }
 
-        // Check if the index reaqched 64
-        if board_state_index != 64 {
-            panic!("The board state is not complete");
+    /// Plays `mv` if it is legal and returns the full FEN of the resulting position, or
+    /// `None` if the move was rejected and the game left unchanged.
+    pub fn play_and_fen(&mut self, mv: Move) -> Option<String> {
+        if !self.is_legal(&mv) || !self.play_move_ob(mv) {
+            return None;
         }
+        Some(self.get_fen())
+    }
 
-        // Set the turn
-        self.white_turn = turn == "w";
+    /// Plays the move from `source` to `target`, promoting to `promotion` ("q", "r", "b" or
+    /// "n" in either case, or "" for none), and returns the full FEN of the new position.
+    /// Unlike `play_move_from_string`, bad squares, bad promotions and illegal moves are
+    /// reported as errors, and the game is left unchanged.
+    pub fn try_play_move(
+        &mut self,
+        source: &str,
+        target: &str,
+        promotion: &str,
+    ) -> Result<String, CherrisError> {
+        let notation = format!("{}{}{}", source, target, promotion);
+        let mv = Move {
+            source: position_helper::try_letter_to_index(source)?,
+            target: position_helper::try_letter_to_index(target)?,
+            promotion: Move::promotion_from_str(promotion, self.white_turn).ok_or_else(|| {
+                MoveError::InvalidPromotion {
+                    index: 0,
+                    notation: notation.clone(),
+                }
+            })?,
+            flags: 0,
+        };
+        let fen = self
+            .play_and_fen(mv)
+            .ok_or(MoveError::IllegalMove { index: 0, notation })?;
+        Ok(fen)
+    }
 
-        // Set castling options for board
-        for c in castling_options.chars() {
-            match c {
-                'K' => self.board.castling |= 8u8,
-                'Q' => self.board.castling |= 4u8,
-                'k' => self.board.castling |= 2u8,
-                'q' => self.board.castling |= 1u8,
-                _ => (),
+    /// Returns every square attacked by the piece on `square`, including squares held by
+    /// pieces of its own color, regardless of whether moving there would be legal.
+    /// Returns an empty vector if the square is empty.
+    pub fn attacks_from(&self, square: u8) -> Vec<u8> {
+        match self.board.state.get(square as usize) {
+            Some(&piece_bits) if piece_bits != 0 => {
+                Piece::init_from_binary(piece_bits).attacked_squares(square, &self.board)
             }
+            _ => vec![],
         }
-
-        // Set the en passant
-        self.en_passant = en_passant.to_string();
-        if en_passant != "-" {
-            self.board.en_passant = position_helper::letter_to_index(en_passant.to_string());
-        } else {
-            self.board.en_passant = 0;
-        }
-
-        // Set the half move clock
-        self.half_move_clock = half_move_clock.parse::<i32>().unwrap();
-
-        // Set the full move number
-        self.full_move_number = full_move_number.parse::<i32>().unwrap();
     }
 
-    fn get_fen(&self) -> String {
-        let mut fen_string = self.get_fen_simple();
+    /// Returns the position as a FEN (Forsyth–Edwards Notation) record, with all six fields.
+    /// Unlike `ChessGame::get_fen`, it needs no trait in scope.
+    ///
+    /// ```
+    /// use cherris::Game;
+    ///
+    /// assert_eq!(
+    ///     Game::init().fen(),
+    ///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    /// );
+    /// ```
+    pub fn fen(&self) -> String {
+        let mut fen_string = self.simple_fen();
 
         // Append the turn
         if self.white_turn {
@@ -402,7 +384,7 @@ impl ChessGame for Game {
 
     /// Returns a simplified FEN (Forsyth–Edwards Notation) string representing the current game state.
     /// The simplified FEN string does not include the turn, castling options, en passant, half move clock, and full move number.
-    fn get_fen_simple(&self) -> String {
+    pub fn simple_fen(&self) -> String {
         let mut fen_string = "".to_string();
         let mut empty_count = 0;
 
@@ -434,37 +416,1683 @@ impl ChessGame for Game {
         fen_string
     }
 
-    fn play_move(&mut self, mv: Move, check_move_legality: bool) -> bool {
-        if self.game_done {
-            let _winning_side: String = if self.white_turn {
-                "Black".to_string()
-            } else {
-                "White".to_string()
-            };
-
-            return false;
+    /// Returns the Zobrist hash of the position, covering the pieces, castling rights,
+    /// en passant square and side to move. Transpositions get the same hash, and the
+    /// engine's evaluation cache is keyed on the same Zobrist keys.
+    pub fn hash(&self) -> u64 {
+        let hash = self.board.zobrist_hash();
+        if self.white_turn {
+            hash
+        } else {
+            hash ^ Board::zobrist_black_to_move()
         }
+    }
 
-        // Get the piece at the source index
-        let piece_bits = self.board.state.get(mv.source as usize).unwrap_or(&0u8);
+    /// Returns the full move number, which starts at 1 and goes up after each black move.
+    pub fn full_move_number(&self) -> i32 {
+        self.full_move_number
+    }
 
-        // bool for the half move clock
-        let mut piece_taken = false;
+    /// Returns the number of half moves since the last capture or pawn move, as used by the
+    /// fifty-move rule.
+    pub fn half_move_clock(&self) -> i32 {
+        self.half_move_clock
+    }
 
-        let king_moved = false;
+    /// Returns the number of half moves played since the start of the game, derived from
+    /// the full move number and the side to move, so it is 0 in the initial position.
+    pub fn ply(&self) -> u32 {
+        let black_to_move = if self.white_turn { 0 } else { 1 };
+        ((self.full_move_number - 1).max(0) * 2 + black_to_move) as u32
+    }
 
-        if piece_bits == &0u8 {
-            return false;
+    /// Returns the legal moves of the side to move grouped by source square, both in
+    /// algebraic notation. The targets of each square are sorted, and a promotion square
+    /// is listed once even though there is a move for every promotion piece.
+    pub fn legal_moves_map(&self) -> HashMap<String, Vec<String>> {
+        let mut moves_map: HashMap<String, Vec<String>> = HashMap::new();
+        for mv in self.legal_moves_for(self.turn()) {
+            moves_map
+                .entry(position_helper::index_to_letter(mv.source))
+                .or_default()
+                .push(position_helper::index_to_letter(mv.target));
         }
-
-        let piece = Piece::init_from_binary(*piece_bits);
-
-        // Check if turn is correct
-        if piece.is_white != self.white_turn {
-            return false;
+        for targets in moves_map.values_mut() {
+            targets.sort();
+            targets.dedup();
         }
+        moves_map
+    }
 
-        if check_move_legality {
+    /// Returns true if any piece of the given color attacks `square`, whether or not the
+    /// square is occupied and whether or not the attacker could legally move there.
+    pub fn is_attacked(&self, square: u8, by_white: bool) -> bool {
+        self.board.attackers_of(square, by_white) != 0
+    }
+
+    /// Returns the side to move.
+    pub fn turn(&self) -> Color {
+        Color::from(self.white_turn)
+    }
+
+    /// Returns the legal moves of `color`, like `ChessGame::get_legal_moves`.
+    pub fn legal_moves_for(&self, color: Color) -> Vec<Move> {
+        self.get_legal_moves(color.is_white())
+    }
+
+    /// Returns the pseudolegal moves of `color`, captures included, like
+    /// `ChessGame::get_all_moves_for_color`.
+    pub fn all_moves_for(&self, color: Color) -> Vec<Move> {
+        self.get_all_moves_for_color(color.is_white())
+    }
+
+    /// Returns true if the side to move is in check.
+    pub fn is_in_check(&self) -> bool {
+        self.king_in_check(self.white_turn)
+    }
+
+    /// Returns true if playing `mv` would put the opponent's king in check, directly or by
+    /// uncovering an attack. Returns false if the move cannot be played.
+    pub fn gives_check(&self, mv: &Move) -> bool {
+        let mut game_copy = self.clone();
+        game_copy.play_move_ob(*mv) && game_copy.is_in_check()
+    }
+
+    /// Returns the piece `mv` would capture and its color, without playing the move. For an
+    /// en passant capture this is the pawn beside the target square. Returns `None` if the
+    /// move captures nothing or there is no piece to move.
+    pub fn capture_target(&self, mv: &Move) -> Option<(PieceType, bool)> {
+        let (piece_type, is_white) = self.board.piece_on(mv.source)?;
+        let en_passant = piece_type == PieceType::Pawn
+            && self.board.en_passant != 0
+            && mv.target == self.board.en_passant;
+        let captured_square = match (en_passant, is_white) {
+            (true, true) => mv.target + ROW,
+            (true, false) => mv.target - ROW,
+            (false, _) => mv.target,
+        };
+        self.board
+            .piece_on(captured_square)
+            .filter(|&(_, captured_is_white)| captured_is_white != is_white)
+    }
+
+    /// Returns the static exchange evaluation of `mv`: the material, in centipawns, that the
+    /// side making it wins or loses once both sides have recaptured on the target square
+    /// for as long as it pays, cheapest piece first. Pieces lined up behind an attacker
+    /// join in as it leaves. Returns 0 if the move captures nothing.
+    pub fn see(&self, mv: &Move) -> i32 {
+        let Some((victim, _)) = self.capture_target(mv) else {
+            return 0;
+        };
+        let Some((mover, is_white)) = self.board.piece_on(mv.source) else {
+            return 0;
+        };
+
+        // Only the board state is updated, which is all attackers_of looks at
+        let mut board = self.board.clone();
+        if board.state[mv.target as usize] == 0 {
+            let captured = if is_white {
+                mv.target + ROW
+            } else {
+                mv.target - ROW
+            };
+            board.state[captured as usize] = 0;
+        }
+        board.state[mv.target as usize] = board.state[mv.source as usize];
+        board.state[mv.source as usize] = 0;
+
+        // gains[i] is what the side making capture i has won if the exchange stops there
+        let mut gains = vec![victim.value()];
+        let mut on_target = mover.value();
+        let mut side = !is_white;
+        loop {
+            let mut attackers = board.attackers_of(mv.target, side);
+            let mut cheapest: Option<(u8, i32)> = None;
+            while attackers != 0 {
+                let square = (attackers.trailing_zeros() ^ 56) as u8;
+                attackers &= attackers - 1;
+                let value = Piece::init_from_binary(board.state[square as usize])
+                    .class
+                    .value();
+                if cheapest.is_none_or(|(_, cheapest_value)| value < cheapest_value) {
+                    cheapest = Some((square, value));
+                }
+            }
+            let Some((square, value)) = cheapest else {
+                break;
+            };
+            gains.push(on_target - gains[gains.len() - 1]);
+            on_target = value;
+            board.state[mv.target as usize] = board.state[square as usize];
+            board.state[square as usize] = 0;
+            side = !side;
+        }
+
+        // Each side only recaptures when it does not lose by it
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+
+    /// Returns true if the position is calm enough to be scored statically: the side to move
+    /// is not in check and has no legal capture that wins material by `see`.
+    pub fn is_quiet(&self) -> bool {
+        if self.is_in_check() {
+            return false;
+        }
+        !self
+            .get_capture_moves()
+            .iter()
+            .any(|mv| self.see(mv) > 0 && self.is_legal(mv))
+    }
+
+    /// Returns the square of the side to move's king if it is in check, so it can be highlighted.
+    pub fn checked_king_square(&self) -> Option<u8> {
+        let king_position = self.board.king_square(self.white_turn)?;
+        self.is_in_check().then_some(king_position)
+    }
+
+    /// Checks that the position could arise in a game: each side has exactly one king, no
+    /// pawn stands on the first or eighth rank, the side that just moved is not in check and
+    /// every castling right has its king and rook on their home squares.
+    /// Returns the first problem found.
+    pub fn validate_position(&self) -> Result<(), PositionError> {
+        let kings = [
+            (true, self.board.bitboard[5]),
+            (false, self.board.bitboard[11]),
+        ];
+        for (white, king_bitboard) in kings {
+            let count = king_bitboard.count_ones();
+            if count != 1 {
+                return Err(PositionError::KingCount { white, count });
+            }
+        }
+
+        let back_ranks = (0..8u8).chain(56..64);
+        for square in back_ranks {
+            let piece_bits = self.board.state[square as usize];
+            if piece_bits != 0 && Piece::init_from_binary(piece_bits).class == PieceType::Pawn {
+                return Err(PositionError::PawnOnBackRank(square));
+            }
+        }
+
+        if self.king_in_check(!self.white_turn) {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        // Castling bit, FEN letter, king and rook home squares and color
+        let castling_rights = [
+            (8u8, 'K', 60, 63, true),
+            (4u8, 'Q', 60, 56, true),
+            (2u8, 'k', 4, 7, false),
+            (1u8, 'q', 4, 0, false),
+        ];
+        for (bit, right, king, rook, white) in castling_rights {
+            if self.board.castling & bit == 0 {
+                continue;
+            }
+            let at_home = |square: u8, piece_type: PieceType| {
+                self.board.state[square as usize] == piece_type.to_binary(white)
+            };
+            if !at_home(king, PieceType::King) || !at_home(rook, PieceType::Rook) {
+                return Err(PositionError::InvalidCastlingRight(right));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if neither side has enough material left to deliver checkmate.
+    /// This covers king against king, king and a single minor piece against king,
+    /// and kings with one bishop each on squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces: Vec<(u8, Piece)> = vec![];
+        for square in 0..64u8 {
+            let piece_bits = self.board.state[square as usize];
+            if piece_bits == 0 {
+                continue;
+            }
+            let piece = Piece::init_from_binary(piece_bits);
+            match piece.class {
+                PieceType::King => {}
+                PieceType::Knight | PieceType::Bishop => minor_pieces.push((square, piece)),
+                _ => return false,
+            }
+        }
+
+        match minor_pieces.as_slice() {
+            [] | [_] => true,
+            [(first_square, first), (second_square, second)] => {
+                let square_color = |square: u8| {
+                    (position_helper::get_row(square) + position_helper::get_col(square)) % 2
+                };
+                first.class == PieceType::Bishop
+                    && second.class == PieceType::Bishop
+                    && first.is_white != second.is_white
+                    && square_color(*first_square) == square_color(*second_square)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true for a dead position made of kings and pawns locked against each other:
+    /// every pawn is blocked by an enemy pawn, no pawn can capture, and neither king can
+    /// walk to an enemy pawn it could take. The pawns can then never move, so no series of
+    /// legal moves leads to checkmate (FIDE 5.2.2). Other dead positions are not detected.
+    pub fn is_dead_position(&self) -> bool {
+        const FILE_A: u64 = 0x0101_0101_0101_0101;
+        const FILE_H: u64 = 0x8080_8080_8080_8080;
+
+        // Bitboards count from a1, so white pawns move towards higher bits
+        let bitboards = &self.board.bitboard;
+        let pieces = bitboards[1..5].iter().chain(&bitboards[7..11]);
+        if pieces.fold(0, |all, bitboard| all | bitboard) != 0 || self.board.en_passant != 0 {
+            return false;
+        }
+        let (white_pawns, black_pawns) = (bitboards[0], bitboards[6]);
+        if (white_pawns << 8) & !black_pawns != 0 || (black_pawns >> 8) & !white_pawns != 0 {
+            return false;
+        }
+        let white_attacks = ((white_pawns << 7) & !FILE_H) | ((white_pawns << 9) & !FILE_A);
+        let black_attacks = ((black_pawns >> 9) & !FILE_H) | ((black_pawns >> 7) & !FILE_A);
+        if white_attacks & black_pawns != 0 || black_attacks & white_pawns != 0 {
+            return false;
+        }
+
+        let king_steps = |squares: u64| {
+            let sideways = squares | ((squares << 1) & !FILE_A) | ((squares >> 1) & !FILE_H);
+            sideways | (sideways << 8) | (sideways >> 8)
+        };
+        // Flood fills the squares the king can ever reach, then looks for an enemy pawn next
+        // to them that no other pawn defends
+        let can_win_a_pawn = |king: u64, enemy_pawns: u64, enemy_attacks: u64| {
+            let open = !(white_pawns | black_pawns | enemy_attacks);
+            let mut reachable = king;
+            loop {
+                let next = (king_steps(reachable) & open) | king;
+                if next == reachable {
+                    break;
+                }
+                reachable = next;
+            }
+            king_steps(reachable) & enemy_pawns & !enemy_attacks != 0
+        };
+        let (white_king, black_king) = (bitboards[5], bitboards[11]);
+        if white_king & black_attacks != 0 || black_king & white_attacks != 0 {
+            return false;
+        }
+        !can_win_a_pawn(white_king, black_pawns, black_attacks)
+            && !can_win_a_pawn(black_king, white_pawns, white_attacks)
+    }
+
+    /// Counts the leaf nodes of the legal move tree up to the given depth (performance test).
+    /// The result can be compared against published node counts to validate move generation.
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves_for(self.turn());
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut count = 0;
+        for mv in moves {
+            if self.play_move_ob(mv) {
+                count += self.perft(depth - 1);
+                self.undo_move();
+            }
+        }
+        count
+    }
+
+    /// Runs perft like `perft`, calling `on_root_move` with each root move and the node count
+    /// of its subtree as soon as that subtree is done, so long runs can report progress.
+    /// Returns the total node count.
+    pub fn perft_with_callback(
+        &mut self,
+        depth: u8,
+        on_root_move: &mut dyn FnMut(Move, u64),
+    ) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut count = 0;
+        for mv in self.legal_moves_for(self.turn()) {
+            if self.play_move_ob(mv) {
+                let nodes = self.perft(depth - 1);
+                self.undo_move();
+                on_root_move(mv, nodes);
+                count += nodes;
+            }
+        }
+        count
+    }
+
+    /// Runs perft like `perft`, with the root moves split across threads. Each subtree is
+    /// counted on its own copy of the game, so the total matches `perft`.
+    pub fn perft_parallel(&self, depth: u8) -> u64 {
+        if depth <= 1 {
+            return self.clone().perft(depth);
+        }
+
+        self.legal_moves_for(self.turn())
+            .into_par_iter()
+            .map(|mv| {
+                let mut game = self.clone();
+                if game.play_move_ob(mv) {
+                    game.perft(depth - 1)
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+
+    /// Undoes up to `count` moves, stopping early when the history runs out.
+    /// Returns the number of moves that were undone.
+    pub fn undo_moves(&mut self, count: usize) -> usize {
+        (0..count).take_while(|_| self.undo_move()).count()
+    }
+
+    /// Returns the FEN of every position before the current one, oldest first, so that
+    /// `history()[n]` is the position after the first `n` moves.
+    pub fn history(&self) -> &[String] {
+        &self.previous_fen_positions
+    }
+
+    /// Rewinds the game to the position after its first `ply` moves, dropping the later ones.
+    /// Returns `false` without changing the game if `ply` is beyond the current position.
+    pub fn goto(&mut self, ply: usize) -> bool {
+        let current_ply = self.previous_fen_positions.len();
+        if ply > current_ply {
+            return false;
+        }
+        self.undo_moves(current_ply - ply);
+        true
+    }
+
+    /// Resets the game to the starting position, or to `start_fen` if given, and replays
+    /// `moves` in UCI notation, as in the UCI "position startpos moves e2e4 ..." command.
+    /// Stops at the first malformed or illegal move and reports its index; the moves before
+    /// it are left on the board.
+    pub fn set_from_moves(
+        &mut self,
+        start_fen: Option<&str>,
+        moves: &[&str],
+    ) -> Result<(), MoveError> {
+        self.restart();
+        if let Some(fen) = start_fen {
+            self.load_fen(fen);
+        }
+
+        for (index, notation) in moves.iter().enumerate() {
+            let Some(mv) = Move::from_uci(notation) else {
+                return Err(MoveError::InvalidNotation {
+                    index,
+                    notation: notation.to_string(),
+                });
+            };
+            if !self.is_legal(&mv) || !self.play_move(mv, false) {
+                return Err(MoveError::IllegalMove {
+                    index,
+                    notation: notation.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a game in PGN and returns it with every move played, so the whole game is in
+    /// its history and can be stepped through or undone back to its first position.
+    /// The game starts from the `FEN` tag if there is one. Comments, variations, move numbers,
+    /// annotations and the result are skipped.
+    pub fn from_pgn(pgn: &str) -> Result<Game, CherrisError> {
+        let mut game = Game::init();
+        let mut movetext = String::new();
+        for line in pgn.lines() {
+            let line = line.trim();
+            if let Some(tag) = line.strip_prefix('[') {
+                if let Some(fen) = tag.strip_prefix("FEN ") {
+                    game.try_set_from_fen(fen.trim_end_matches(']').trim().trim_matches('"'))?;
+                }
+            } else {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+
+        for (index, san) in Game::pgn_moves(&movetext).into_iter().enumerate() {
+            let Some(mv) = game.san_to_move(&san) else {
+                return Err(MoveError::IllegalMove {
+                    index,
+                    notation: san,
+                }
+                .into());
+            };
+            game.play_move_ob(mv);
+        }
+        Ok(game)
+    }
+
+    /// Replaces the game with the one read from `pgn`, as `from_pgn` does. The game is left
+    /// unchanged if the PGN cannot be read or has an illegal move.
+    pub fn load_pgn(&mut self, pgn: &str) -> Result<(), CherrisError> {
+        *self = Game::from_pgn(pgn)?;
+        Ok(())
+    }
+
+    /// Returns the SAN moves of PGN movetext, in order, without the comments, variations,
+    /// move numbers, numeric annotations and result.
+    fn pgn_moves(movetext: &str) -> Vec<String> {
+        let mut moves = vec![];
+        let mut token = String::new();
+        let mut variation_depth = 0;
+        let mut in_comment = false;
+        let mut in_line_comment = false;
+        for c in movetext.chars().chain([' ']) {
+            match c {
+                _ if in_line_comment => in_line_comment = c != '\n',
+                _ if in_comment => in_comment = c != '}',
+                '{' => in_comment = true,
+                ';' => in_line_comment = true,
+                '(' => variation_depth += 1,
+                ')' => variation_depth -= 1,
+                _ if variation_depth > 0 => (),
+                c if c.is_whitespace() || c == '.' => {
+                    let is_move_number = token.chars().all(|c| c.is_ascii_digit());
+                    let is_result = ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str());
+                    if !is_move_number && !is_result && !token.starts_with('$') {
+                        moves.push(token.clone());
+                    }
+                    token.clear();
+                }
+                c => token.push(c),
+            }
+        }
+        moves
+    }
+
+    /// Sets the position from a FEN record, checking it first. The half move clock and full
+    /// move number may be left out, in which case they default to 0 and 1.
+    /// The game is left unchanged if the record is invalid.
+    pub fn try_set_from_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let fen = Game::checked_fen(fen)?;
+        self.previous_fen_positions.clear();
+        self.load_fen(&fen);
+        Ok(())
+    }
+
+    /// Sets the position from a FEN record, writing the bitboards while the pieces are read
+    /// instead of rebuilding them from the board state afterwards. Meant for bulk loading
+    /// positions, such as a tuner reading an EPD file. The record is checked the same way as
+    /// in `try_set_from_fen`, and the game is left unchanged if it is invalid.
+    pub fn set_from_fen_direct(&mut self, fen: &str) -> Result<(), FenError> {
+        let fen = Game::checked_fen(fen)?;
+        let fields: Vec<&str> = fen.split(' ').collect();
+
+        // The piece placement has been checked, so every rank covers its eight squares
+        self.board.set_pieces_from_fen(fields[0]);
+        self.previous_fen_positions.clear();
+        self.set_fen_state(fields[1], fields[2], fields[3], fields[4], fields[5]);
+        Ok(())
+    }
+
+    /// Checks a FEN record and returns it with single spaces between the fields, a lowercase
+    /// en passant square and both move clocks filled in.
+    fn checked_fen(fen: &str) -> Result<String, FenError> {
+        let mut fields = fen.split_whitespace();
+        let mut next_field = |name| fields.next().ok_or(FenError::MissingField(name));
+        let board = next_field("piece placement")?;
+        let turn = next_field("side to move")?;
+        let castling = next_field("castling")?;
+        // Some sites write the en passant square in capitals
+        let en_passant = &next_field("en passant")?.to_ascii_lowercase();
+        Game::validate_fen_fields(board, turn, castling, en_passant)?;
+
+        let mut move_clock = |default: &str| {
+            let clock = fields.next().unwrap_or(default);
+            match clock.parse::<i32>() {
+                Ok(value) if value >= 0 => Ok(value),
+                _ => Err(FenError::InvalidMoveClock(clock.to_string())),
+            }
+        };
+        let half_move_clock = move_clock("0")?;
+        let full_move_number = move_clock("1")?;
+
+        Ok(format!(
+            "{} {} {} {} {} {}",
+            board, turn, castling, en_passant, half_move_clock, full_move_number
+        ))
+    }
+
+    /// Sets the position from a FEN record, leaving the history alone so that undoing a move
+    /// can restore the previous position.
+    fn load_fen(&mut self, fen: &str) {
+        // Reset the board
+        self.board.state = [0u8; 64];
+
+        // Split the fen, pasted records often have extra spaces or tabs between fields
+        let mut fen_split = fen.split_whitespace();
+        let board_state = fen_split.next().unwrap();
+        let turn = fen_split.next().unwrap();
+        let castling_options = fen_split.next().unwrap();
+        let en_passant = fen_split.next().unwrap();
+        // The move clocks are often left out, so fall back to a fresh count
+        let half_move_clock = fen_split.next().unwrap_or("0");
+        let full_move_number = fen_split.next().unwrap_or("1");
+
+        // Set the board state
+        let mut board_state_index = 0;
+        for c in board_state.chars() {
+            if c == '/' {
+                continue;
+            }
+            if c.is_numeric() {
+                let num = c.to_digit(10).unwrap();
+                board_state_index += num;
+            } else {
+                // Set the piece
+                let Some(piece_type) = PieceType::from_char(c) else {
+                    panic!("This piece does not exist!");
+                };
+                let piece = piece_type.to_binary(c.is_uppercase());
+                let index: usize = board_state_index.try_into().unwrap();
+                self.board.state[index] = piece;
+                board_state_index += 1;
+            }
+        }
+
+        // Check if the index reaqched 64
+        if board_state_index != 64 {
+            panic!("The board state is not complete");
+        }
+        self.board.sync_bitboards();
+        self.board.recompute_material();
+
+        self.set_fen_state(
+            turn,
+            castling_options,
+            en_passant,
+            half_move_clock,
+            full_move_number,
+        );
+    }
+
+    /// Sets everything but the pieces from the remaining FEN fields.
+    fn set_fen_state(
+        &mut self,
+        turn: &str,
+        castling_options: &str,
+        en_passant: &str,
+        half_move_clock: &str,
+        full_move_number: &str,
+    ) {
+        // Set the turn
+        self.white_turn = turn == "w";
+
+        // Set castling options for board
+        self.board.castling = 0;
+        for c in castling_options.chars() {
+            match c {
+                'K' => self.board.castling |= 8u8,
+                'Q' => self.board.castling |= 4u8,
+                'k' => self.board.castling |= 2u8,
+                'q' => self.board.castling |= 1u8,
+                _ => (),
+            }
+        }
+
+        // Set the en passant
+        self.en_passant = en_passant.to_string();
+        if en_passant != "-" {
+            self.board.en_passant = position_helper::try_letter_to_index(en_passant).unwrap();
+        } else {
+            self.board.en_passant = 0;
+        }
+
+        // Set the half move clock
+        self.half_move_clock = half_move_clock.parse::<i32>().unwrap();
+
+        // Set the full move number
+        self.full_move_number = full_move_number.parse::<i32>().unwrap();
+    }
+
+    /// Sets the position from an EPD record: the first four FEN fields followed by operations
+    /// such as `bm Qxd5; id "test 1";`. The `hmvc` and `fmvn` operations set the move clocks,
+    /// which otherwise start at 0 and 1. The game is left unchanged if the record is invalid.
+    pub fn set_from_epd(&mut self, epd: &str) -> Result<EpdOps, FenError> {
+        let mut fields = epd.trim().splitn(5, char::is_whitespace);
+        let mut next_field = |name| {
+            let field = fields.next().filter(|x| !x.is_empty());
+            field.ok_or(FenError::MissingField(name))
+        };
+        let board = next_field("piece placement")?;
+        let turn = next_field("side to move")?;
+        let castling = next_field("castling")?;
+        let en_passant = next_field("en passant")?;
+        Game::validate_fen_fields(board, turn, castling, en_passant)?;
+
+        let mut ops = EpdOps::default();
+        let mut half_move_clock = 0;
+        let mut full_move_number = 1;
+        for operation in fields.next().unwrap_or("").split(';') {
+            let operation = operation.trim();
+            if operation.is_empty() {
+                continue;
+            }
+            let (opcode, operands) = operation.split_once(' ').unwrap_or((operation, ""));
+            let operands = operands.trim().trim_matches('"').to_string();
+            let moves = || operands.split_whitespace().map(String::from).collect();
+            match opcode {
+                "id" => ops.id = Some(operands.clone()),
+                "bm" => ops.best_moves = moves(),
+                "am" => ops.avoid_moves = moves(),
+                "hmvc" | "fmvn" => {
+                    let Ok(value) = operands.parse::<i32>() else {
+                        return Err(FenError::InvalidOperation(operation.to_string()));
+                    };
+                    if opcode == "hmvc" {
+                        half_move_clock = value;
+                    } else {
+                        full_move_number = value;
+                    }
+                }
+                _ => (),
+            }
+            ops.operations.push((opcode.to_string(), operands));
+        }
+
+        self.restart();
+        self.load_fen(&format!(
+            "{} {} {} {} {} {}",
+            board, turn, castling, en_passant, half_move_clock, full_move_number
+        ));
+        Ok(ops)
+    }
+
+    /// Returns the position as an EPD record, with the move clocks as `hmvc` and `fmvn` operations.
+    pub fn get_epd(&self) -> String {
+        let fen = self.get_fen();
+        let fields: Vec<&str> = fen.split(' ').collect();
+        format!(
+            "{} hmvc {}; fmvn {};",
+            fields[..4].join(" "),
+            self.half_move_clock,
+            self.full_move_number
+        )
+    }
+
+    /// Returns true if `board` is a piece placement of eight ranks of eight squares.
+    fn is_valid_piece_placement(board: &str) -> bool {
+        let ranks: Vec<&str> = board.split('/').collect();
+        let valid_rank = |rank: &&str| {
+            let mut squares = 0;
+            for c in rank.chars() {
+                match c.to_digit(10) {
+                    Some(empty @ 1..=8) => squares += empty,
+                    Some(_) => return false,
+                    None if PieceType::from_char(c).is_some() => squares += 1,
+                    None => return false,
+                }
+            }
+            squares == 8
+        };
+        ranks.len() == 8 && ranks.iter().all(valid_rank)
+    }
+
+    /// Checks the first four fields of a FEN record.
+    fn validate_fen_fields(
+        board: &str,
+        turn: &str,
+        castling: &str,
+        en_passant: &str,
+    ) -> Result<(), FenError> {
+        if !Game::is_valid_piece_placement(board) {
+            return Err(FenError::InvalidBoard(board.to_string()));
+        }
+
+        if turn != "w" && turn != "b" {
+            return Err(FenError::InvalidSideToMove(turn.to_string()));
+        }
+
+        // Each right may appear once, in the order KQkq
+        let rights: Option<Vec<usize>> = castling.chars().map(|c| "KQkq".find(c)).collect();
+        let valid_castling = castling == "-"
+            || rights.is_some_and(|rights| rights.windows(2).all(|pair| pair[0] < pair[1]));
+        if !valid_castling {
+            return Err(FenError::InvalidCastling(castling.to_string()));
+        }
+
+        let en_passant_chars: Vec<char> = en_passant.chars().collect();
+        let valid_en_passant = match en_passant_chars[..] {
+            ['-'] => true,
+            [file, rank] => ('a'..='h').contains(&file) && (rank == '3' || rank == '6'),
+            _ => false,
+        };
+        if !valid_en_passant {
+            return Err(FenError::InvalidEnPassant(en_passant.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the same position seen from the other side: the board is flipped vertically,
+    /// the piece colors, castling rights and side to move are swapped, and the en passant
+    /// square is mirrored. The move history is not carried over.
+    pub fn mirror(&self) -> Game {
+        let board = self.board.mirror();
+        let en_passant = if board.en_passant == 0 {
+            "-".to_string()
+        } else {
+            position_helper::index_to_letter(board.en_passant)
+        };
+        Game {
+            white_turn: !self.white_turn,
+            previous_fen_positions: vec![],
+            board,
+            game_done: self.game_done,
+            en_passant,
+            half_move_clock: self.half_move_clock,
+            full_move_number: self.full_move_number,
+        }
+    }
+
+    /// Returns the pieces each side is missing compared to the starting position, as
+    /// `(white_pieces, black_pieces)`, ordered from the most to the least valuable.
+    ///
+    /// Captures are inferred from piece counts rather than the move history, so this is an
+    /// approximation after promotions: a promoted pawn shows up as a captured pawn, and the
+    /// promoted piece hides one capture of its own type.
+    pub fn captured_material(&self) -> (Vec<PieceType>, Vec<PieceType>) {
+        let starting_complement = [
+            (PieceType::Queen, 1),
+            (PieceType::Rook, 2),
+            (PieceType::Bishop, 2),
+            (PieceType::Knight, 2),
+            (PieceType::Pawn, 8),
+        ];
+
+        let mut white_captured = vec![];
+        let mut black_captured = vec![];
+        for (piece_type, starting_count) in starting_complement {
+            for (is_white, captured) in [(true, &mut white_captured), (false, &mut black_captured)]
+            {
+                let piece_bits = piece_type.to_binary(is_white);
+                let on_board = self
+                    .board
+                    .state
+                    .iter()
+                    .filter(|x| **x == piece_bits)
+                    .count();
+                for _ in on_board..starting_count {
+                    captured.push(piece_type.clone());
+                }
+            }
+        }
+        (white_captured, black_captured)
+    }
+
+    /// Returns the status of the game for the side to move.
+    pub fn status(&self) -> GameStatus {
+        if self.is_insufficient_material() {
+            return GameStatus::InsufficientMaterial;
+        }
+        if self.legal_moves_for(self.turn()).is_empty() {
+            return if self.king_in_check(self.white_turn) {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        if self.is_dead_position() {
+            return GameStatus::DeadPosition;
+        }
+        // Unlike the fifty-move rule and threefold repetition, these draws need no claim
+        if self.half_move_clock >= SEVENTY_FIVE_MOVE_RULE {
+            return GameStatus::SeventyFiveMoveRule;
+        }
+        if self.repetition_count() >= 5 {
+            return GameStatus::FivefoldRepetition;
+        }
+        GameStatus::Ongoing
+    }
+
+    /// Returns true if the side to move may claim a draw under the fifty-move rule: 50 moves
+    /// by each side have passed without a capture or pawn move, and the last of them did not
+    /// checkmate. The game only ends if the draw is claimed, so `status` does not report it.
+    pub fn can_claim_fifty_move_draw(&self) -> bool {
+        self.half_move_clock >= FIFTY_MOVE_RULE
+            && !(self.is_in_check() && self.legal_moves_for(self.turn()).is_empty())
+    }
+
+    /// Returns how many times the current position has occurred in the game, counting the
+    /// current occurrence. Positions are the same when the pieces, side to move, castling
+    /// rights and en passant square match; the move clocks are ignored.
+    pub fn repetition_count(&self) -> usize {
+        let current = self.get_fen();
+        let key = Game::position_key(&current);
+        1 + self
+            .previous_fen_positions
+            .iter()
+            .filter(|fen| Game::position_key(fen) == key)
+            .count()
+    }
+
+    /// Returns the part of a FEN that identifies the position when looking for repetitions:
+    /// the pieces, side to move, castling rights and en passant square. The half move clock
+    /// and full move number are cut off, since they change every ply. The history keeps
+    /// full FENs so that undoing a move restores the counters exactly.
+    pub fn position_key(fen: &str) -> &str {
+        let fen = fen.trim();
+        fen.match_indices(' ')
+            .nth(3)
+            .map_or(fen, |(index, _)| &fen[..index])
+    }
+
+    /// Picks a random legal move for the side to move, or `None` if there is none.
+    ///
+    /// With `avoid_mate_in_one` set, moves that let the opponent mate straight away are
+    /// skipped, unless every move does.
+    pub fn random_move<R: Rng>(&self, rng: &mut R, avoid_mate_in_one: bool) -> Option<Move> {
+        self.pick_random_move(rng, avoid_mate_in_one, false)
+    }
+
+    /// Picks a random legal move like `random_move`, but also skips moves that hang the
+    /// moved piece (see `hangs_piece`) unless every remaining move does.
+    pub fn safe_random_move<R: Rng>(&self, rng: &mut R, avoid_mate_in_one: bool) -> Option<Move> {
+        self.pick_random_move(rng, avoid_mate_in_one, true)
+    }
+
+    /// Returns true if, after `mv`, the moved piece can be taken by a cheaper piece, or is
+    /// attacked and not defended at all. Returns false if the move cannot be played.
+    pub fn hangs_piece(&self, mv: &Move) -> bool {
+        let mut after_move = self.clone();
+        if !after_move.play_move_ob(*mv) {
+            return false;
+        }
+        let value_at = |square: u8| {
+            Piece::init_from_binary(after_move.board.state[square as usize])
+                .class
+                .value()
+        };
+        let moved_value = value_at(mv.target);
+        let attackers: Vec<i32> = (0..64u8)
+            .filter(|&source| {
+                after_move.board.piece_color(source) == Some(after_move.white_turn)
+                    && after_move.attacks_from(source).contains(&mv.target)
+            })
+            .map(value_at)
+            .collect();
+        if attackers.is_empty() {
+            return false;
+        }
+        attackers.iter().any(|&value| value < moved_value)
+            || !after_move.is_attacked(mv.target, self.white_turn)
+    }
+
+    /// Picks a random legal move, narrowing the choice down with each filter in turn as long
+    /// as some move passes it. Avoiding mate comes before keeping pieces safe.
+    fn pick_random_move<R: Rng>(
+        &self,
+        rng: &mut R,
+        avoid_mate_in_one: bool,
+        avoid_hanging: bool,
+    ) -> Option<Move> {
+        let narrow = |moves: Vec<Move>, keep: &dyn Fn(&Move) -> bool| {
+            let kept: Vec<Move> = moves.iter().filter(|mv| keep(mv)).copied().collect();
+            if kept.is_empty() {
+                moves
+            } else {
+                kept
+            }
+        };
+        let mut candidates = self.legal_moves_for(self.turn());
+        if candidates.is_empty() {
+            return None;
+        }
+        if avoid_mate_in_one {
+            candidates = narrow(candidates, &|mv| !self.allows_mate_in_one(mv));
+        }
+        if avoid_hanging {
+            candidates = narrow(candidates, &|mv| !self.hangs_piece(mv));
+        }
+        Some(candidates[rng.gen_range(0..candidates.len())])
+    }
+
+    /// Returns true if, after `mv`, the opponent has a move that checkmates.
+    fn allows_mate_in_one(&self, mv: &Move) -> bool {
+        let mut after_move = self.clone();
+        if !after_move.play_move_ob(*mv) {
+            return false;
+        }
+        after_move
+            .legal_moves_for(after_move.turn())
+            .into_iter()
+            .any(|reply| {
+                let mut after_reply = after_move.clone();
+                after_reply.play_move_ob(reply) && after_reply.status() == GameStatus::Checkmate
+            })
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.board.render())?;
+        write!(f, "FEN: {}", self.get_fen())
+    }
+}
+
+/// One of the two sides. `Game::legal_moves_for` and `Game::all_moves_for` take it in place
+/// of the bools, true for white, of `ChessGame`, and `From<bool>` converts such bools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    /// Returns the other side.
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    /// Returns true for white, matching the bools used by `Board` and `Piece`.
+    pub fn is_white(self) -> bool {
+        self == Color::White
+    }
+}
+
+impl From<bool> for Color {
+    /// Converts a bool that is true for white.
+    fn from(is_white: bool) -> Color {
+        if is_white {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+}
+
+/// Represents the state of a game from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    /// The side to move has legal moves and the game continues.
+    Ongoing,
+    /// The side to move is in check and has no legal moves.
+    Checkmate,
+    /// The side to move is not in check but has no legal moves.
+    Stalemate,
+    /// Neither side can deliver checkmate with the remaining material.
+    InsufficientMaterial,
+    /// The kings and pawns are locked so that no series of legal moves can checkmate.
+    DeadPosition,
+    /// 75 moves by each side have passed without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// The same position has occurred five times.
+    FivefoldRepetition,
+    /// The game was adjudicated a draw for reaching the move limit of an engine match.
+    MoveLimit,
+}
+
+impl GameStatus {
+    /// Returns the name of the status as sent to the frontend.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameStatus::Ongoing => "ongoing",
+            GameStatus::Checkmate => "checkmate",
+            GameStatus::Stalemate => "stalemate",
+            GameStatus::InsufficientMaterial => "insufficient_material",
+            GameStatus::DeadPosition => "dead_position",
+            GameStatus::SeventyFiveMoveRule => "seventy_five_move_rule",
+            GameStatus::FivefoldRepetition => "fivefold_repetition",
+            GameStatus::MoveLimit => "move_limit",
+        }
+    }
+
+    /// Returns true if the game has ended in a draw.
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self,
+            GameStatus::Stalemate
+                | GameStatus::InsufficientMaterial
+                | GameStatus::DeadPosition
+                | GameStatus::SeventyFiveMoveRule
+                | GameStatus::FivefoldRepetition
+                | GameStatus::MoveLimit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Move {
+    pub source: u8,    // source position byte
+    pub target: u8,    // target position byte
+    pub promotion: u8, // piece to promote to
+    /// What kind of move this is, as a combination of the `*_FLAG` constants.
+    /// Set by the move generators; moves built by hand may leave it as 0.
+    pub flags: u8,
+}
+
+/// Moves are equal when they move the same piece to the same square with the same promotion.
+/// The flags only describe the move in its position, so they are not compared; this keeps
+/// hand-built moves comparable with generated ones.
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.target == other.target
+            && self.promotion == other.promotion
+    }
+}
+
+impl Move {
+    /// Builds a move that is not a capture, promotion, castling or double push.
+    pub fn quiet(source: u8, target: u8) -> Move {
+        Move {
+            source,
+            target,
+            promotion: 0,
+            flags: 0,
+        }
+    }
+
+    /// Builds a pawn move that promotes to `piece` of the given color, encoding the
+    /// promotion byte the same way the move generators do.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `piece` is a pawn or a king.
+    pub fn promotion(source: u8, target: u8, piece: PieceType, is_white: bool) -> Move {
+        assert!(
+            !matches!(piece, PieceType::Pawn | PieceType::King),
+            "cannot promote to a {:?}",
+            piece
+        );
+        Move {
+            source,
+            target,
+            promotion: piece.to_binary(is_white),
+            flags: PROMOTION_FLAG,
+        }
+    }
+
+    /// Builds the kingside castling move of the given color, moving the king two squares.
+    pub fn castle_kingside(is_white: bool) -> Move {
+        let king = if is_white { 60 } else { 4 };
+        Move {
+            source: king,
+            target: king + 2,
+            promotion: 0,
+            flags: CASTLE_FLAG,
+        }
+    }
+
+    /// Builds the queenside castling move of the given color, moving the king two squares.
+    pub fn castle_queenside(is_white: bool) -> Move {
+        let king = if is_white { 60 } else { 4 };
+        Move {
+            source: king,
+            target: king - 2,
+            promotion: 0,
+            flags: CASTLE_FLAG,
+        }
+    }
+
+    /// Builds an en passant capture, `target` being the empty square the pawn moves to.
+    pub fn en_passant(source: u8, target: u8) -> Move {
+        Move {
+            source,
+            target,
+            promotion: 0,
+            flags: CAPTURE_FLAG | EN_PASSANT_FLAG,
+        }
+    }
+
+    /// Returns true if the move captures a piece, including en passant.
+    pub fn is_capture(&self) -> bool {
+        self.flags & CAPTURE_FLAG != 0
+    }
+
+    /// Returns true if the move is an en passant capture.
+    pub fn is_en_passant(&self) -> bool {
+        self.flags & EN_PASSANT_FLAG != 0
+    }
+
+    /// Returns true if the move is castling, on either side.
+    pub fn is_castle(&self) -> bool {
+        self.flags & CASTLE_FLAG != 0
+    }
+
+    /// Returns true if the move is a pawn's two-square first move.
+    pub fn is_double_push(&self) -> bool {
+        self.flags & DOUBLE_PUSH_FLAG != 0
+    }
+
+    /// Returns true if the move promotes a pawn.
+    pub fn is_promotion(&self) -> bool {
+        self.flags & PROMOTION_FLAG != 0
+    }
+
+    /// Returns the move in UCI long algebraic notation, e.g. "e2e4" or "e7e8q".
+    pub fn to_uci(&self) -> String {
+        let mut uci = position_helper::index_to_letter(self.source);
+        uci.push_str(&position_helper::index_to_letter(self.target));
+        if self.promotion != 0 {
+            uci.push(Piece::init_from_binary(self.promotion).class.to_char(false));
+        }
+        uci
+    }
+
+    /// Parses a move in UCI long algebraic notation, e.g. "e2e4" or "e7e8q".
+    /// The colour of a promotion piece is taken from the rank the pawn promotes on.
+    /// Returns `None` if the notation is malformed.
+    pub fn from_uci(uci: &str) -> Option<Move> {
+        Move::try_from_uci(uci).ok()
+    }
+
+    /// Parses a move like `from_uci`, telling a malformed move apart from a bad promotion
+    /// piece when it fails.
+    pub fn try_from_uci(uci: &str) -> Result<Move, MoveError> {
+        let invalid = || MoveError::InvalidNotation {
+            index: 0,
+            notation: uci.to_string(),
+        };
+        if !uci.is_ascii() || !(uci.len() == 4 || uci.len() == 5) {
+            return Err(invalid());
+        }
+        let source = position_helper::try_letter_to_index(&uci[0..2]).map_err(|_| invalid())?;
+        let target = position_helper::try_letter_to_index(&uci[2..4]).map_err(|_| invalid())?;
+        let is_white = position_helper::get_row(target) == 0;
+        let promotion = Move::promotion_from_str(&uci[4..], is_white).ok_or_else(|| {
+            MoveError::InvalidPromotion {
+                index: 0,
+                notation: uci.to_string(),
+            }
+        })?;
+
+        Ok(Move {
+            source,
+            target,
+            promotion,
+            flags: 0,
+        })
+    }
+
+    /// Reads a promotion piece letter, in either case, into the piece byte of the given
+    /// color. An empty string means no promotion, and anything else that is not a queen,
+    /// rook, bishop or knight gives `None`.
+    fn promotion_from_str(piece: &str, is_white: bool) -> Option<u8> {
+        let mut chars = piece.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => Some(0),
+            (Some(c), None) => match PieceType::from_char(c)? {
+                PieceType::Pawn | PieceType::King => None,
+                piece_type => Some(piece_type.to_binary(is_white)),
+            },
+            _ => None,
+        }
+    }
+}
+
+pub struct MoveOutput {
+    pub source: u8,
+    pub target: u8,
+    pub promotion: u8,
+    pub taken_piece: u8,
+    pub valid: bool,
+}
+
+/// Error returned when a move, or a list of moves, cannot be read or played.
+/// For a single move, `index` is 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    /// The move at `index` is not valid UCI notation.
+    InvalidNotation { index: usize, notation: String },
+    /// The move at `index` is not legal in the position it was played in.
+    IllegalMove { index: usize, notation: String },
+    /// The move at `index` promotes to something other than a queen, rook, bishop or knight.
+    InvalidPromotion { index: usize, notation: String },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::InvalidNotation { index, notation } => {
+                write!(f, "move {} ({}) is not valid UCI notation", index, notation)
+            }
+            MoveError::IllegalMove { index, notation } => {
+                write!(f, "move {} ({}) is not legal", index, notation)
+            }
+            MoveError::InvalidPromotion { index, notation } => {
+                write!(f, "move {} ({}) has an invalid promotion", index, notation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Error returned when a FEN or EPD record cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The record has fewer fields than required.
+    MissingField(&'static str),
+    /// The piece placement field is not a valid 8x8 board.
+    InvalidBoard(String),
+    /// The side to move is neither "w" nor "b".
+    InvalidSideToMove(String),
+    /// The castling field contains something other than "KQkq" or "-".
+    InvalidCastling(String),
+    /// The en passant field is neither a square nor "-".
+    InvalidEnPassant(String),
+    /// The half move clock or full move number is not a non-negative number.
+    InvalidMoveClock(String),
+    /// An EPD operation is malformed.
+    InvalidOperation(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {} field", field),
+            FenError::InvalidBoard(board) => write!(f, "invalid piece placement: {}", board),
+            FenError::InvalidSideToMove(turn) => write!(f, "invalid side to move: {}", turn),
+            FenError::InvalidCastling(castling) => write!(f, "invalid castling: {}", castling),
+            FenError::InvalidEnPassant(square) => write!(f, "invalid en passant: {}", square),
+            FenError::InvalidMoveClock(clock) => write!(f, "invalid move clock: {}", clock),
+            FenError::InvalidOperation(op) => write!(f, "invalid EPD operation: {}", op),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Error returned when a square name, such as "e4", cannot be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SquareError {
+    /// The name is not a file from a to h followed by a rank from 1 to 8.
+    InvalidSquare(String),
+}
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SquareError::InvalidSquare(square) => write!(f, "invalid square: {}", square),
+        }
+    }
+}
+
+impl std::error::Error for SquareError {}
+
+/// Problem found by `Game::validate_position` in a position that cannot arise in a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    /// A side has no king or more than one.
+    KingCount { white: bool, count: u32 },
+    /// A pawn stands on the first or eighth rank, given as a board index.
+    PawnOnBackRank(u8),
+    /// The side that just moved has left its king in check.
+    OpponentInCheck,
+    /// A castling right, as its FEN letter, whose king or rook is not on its home square.
+    InvalidCastlingRight(char),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::KingCount { white, count } => {
+                let side = if *white { "white" } else { "black" };
+                write!(f, "{} has {} kings", side, count)
+            }
+            PositionError::PawnOnBackRank(square) => {
+                let square = position_helper::index_to_letter(*square);
+                write!(f, "pawn on the back rank: {}", square)
+            }
+            PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionError::InvalidCastlingRight(right) => {
+                write!(f, "castling right {} without king and rook at home", right)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+/// Any error returned by the fallible `try_*` functions of the crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CherrisError {
+    Fen(FenError),
+    Move(MoveError),
+    Square(SquareError),
+    Position(PositionError),
+}
+
+impl fmt::Display for CherrisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CherrisError::Fen(error) => error.fmt(f),
+            CherrisError::Move(error) => error.fmt(f),
+            CherrisError::Square(error) => error.fmt(f),
+            CherrisError::Position(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CherrisError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CherrisError::Fen(error) => Some(error),
+            CherrisError::Move(error) => Some(error),
+            CherrisError::Square(error) => Some(error),
+            CherrisError::Position(error) => Some(error),
+        }
+    }
+}
+
+impl From<FenError> for CherrisError {
+    fn from(error: FenError) -> Self {
+        CherrisError::Fen(error)
+    }
+}
+
+impl From<MoveError> for CherrisError {
+    fn from(error: MoveError) -> Self {
+        CherrisError::Move(error)
+    }
+}
+
+impl From<SquareError> for CherrisError {
+    fn from(error: SquareError) -> Self {
+        CherrisError::Square(error)
+    }
+}
+
+impl From<PositionError> for CherrisError {
+    fn from(error: PositionError) -> Self {
+        CherrisError::Position(error)
+    }
+}
+
+/// Operations attached to an EPD record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpdOps {
+    /// The `id` operation, usually the name of the test position.
+    pub id: Option<String>,
+    /// The moves listed by the `bm` (best move) operation, in SAN.
+    pub best_moves: Vec<String>,
+    /// The moves listed by the `am` (avoid move) operation, in SAN.
+    pub avoid_moves: Vec<String>,
+    /// Every operation as (opcode, operands), in the order they appear.
+    pub operations: Vec<(String, String)>,
+}
+
+/// Implements the `ChessGame` trait for the `Game` struct.
+/// This trait provides methods for playing chess moves, getting legal moves, removing illegal moves, and more.
+impl ChessGame for Game {
+    /// Returns a vector of legal moves for the specified color.
+    /// The `white` parameter indicates whether the moves are for the white player.
+    fn get_legal_moves(&self, is_white: bool) -> Vec<Move> {
+        // define the filter function
+        let moves = self.get_all_moves_for_color(is_white);
+        self.remove_illegal_moves(moves)
+    }
+
+    /// Removes illegal moves from the given vector of pseudolegal moves.
+    /// Returns a new vector containing only the legal moves.
+    fn remove_illegal_moves(&self, moves: Vec<Move>) -> Vec<Move> {
+        let mut game_copy = self.clone();
+        let mut final_moves: Vec<Move> = vec![];
+
+        // No king found
+        if game_copy.board.king_square(game_copy.white_turn).is_none() {
+            let move_vec: Vec<Move> = vec![];
+            return move_vec;
+        }
+
+        let mut king_in_check;
+        for mv in moves {
+            if self.castles_through_check(&mv) {
+                continue;
+            }
+
+            let success = game_copy.play_move_ob(mv);
+            if !success {
+                continue;
+            }
+
+            // check for the original king's positions
+            let Some(king_position) = game_copy.board.king_square(!game_copy.white_turn) else {
+                game_copy.undo_move();
+                continue;
+            };
+
+            king_in_check = false;
+            let oponent_moves = game_copy.all_moves_for(game_copy.turn());
+            for oponent_move in oponent_moves {
+                if oponent_move.target == king_position {
+                    king_in_check = true;
+                    break;
+                }
+            }
+            if !king_in_check {
+                final_moves.push(mv);
+            }
+            game_copy.undo_move();
+        }
+        final_moves
+    }
+
+    /// Returns a vector of capture moves for the current player.
+    /// A capture move is a move that captures an opponent's piece, en passant included.
+    fn get_capture_moves(&self) -> Vec<Move> {
+        let mut moves = self.all_moves_for(self.turn());
+        moves.retain(Move::is_capture);
+        moves
+    }
+
+    /// Returns the pseudolegal moves of the current player that capture nothing: pawn
+    /// pushes and push promotions, castling and piece moves onto empty squares. Together
+    /// with `get_capture_moves` they make up every move of the side to move.
+    fn get_quiet_moves(&self) -> Vec<Move> {
+        let mut moves = self.all_moves_for(self.turn());
+        moves.retain(|mv| !mv.is_capture());
+        moves
+    }
+
+    //TODO: Optimize - use bit check instead of init_from_binary
+    /// Returns a vector of all possible moves for the specified color incluiding captures.
+    /// The `white` parameter indicates whether the moves are for the white player.
+    fn get_all_moves_for_color(&self, white_turn: bool) -> Vec<Move> {
+        let mut moves = vec![];
+
+        for square in 0..64 {
+            let piece = self.board.state[square];
+            if piece == 0 {
+                continue;
+            }
+            // avoid initialising the piece unless we have to get the moves
+            let is_piece_white: bool = piece & WHITE_BIT != 0;
+            if white_turn != is_piece_white {
+                continue;
+            }
+
+            let piece = Piece::init_from_binary(piece);
+            moves.append(&mut piece.possible_moves(square as u8, &self.board));
+        }
+        moves
+    }
+
+    /// Returns a vector of pseudolegal moves for the specified source square.
+    /// Pseudolegal moves are moves that are valid according to the rules of chess,
+    /// but may leave the king in check.
+    /// Only the target squares are returned, see `get_pseudolegal_moves_from` for full moves.
+    /// Returns an empty vector if `source_square` is not a square name.
+    fn get_pseudolegal_moves(&self, source_square: String) -> Vec<String> {
+        let Ok(position_index) = position_helper::try_letter_to_index(&source_square) else {
+            return vec![];
+        };
+        self.get_pseudolegal_moves_from(position_index)
+            .iter()
+            .map(|x| position_helper::index_to_letter(x.target))
+            .collect()
+    }
+
+    /// Returns the pseudolegal moves of the piece on `square`, with their promotion pieces
+    /// and flags. Returns an empty vector if the square is empty or off the board.
+    fn get_pseudolegal_moves_from(&self, square: u8) -> Vec<Move> {
+        match self.board.state.get(square as usize) {
+            Some(&piece_bits) if piece_bits != 0u8 => {
+                Piece::init_from_binary(piece_bits).possible_moves(square, &self.board)
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Restores the position before the last move.
+    /// Returns `false` without changing the position if there is no move to undo.
+    fn undo_move(&mut self) -> bool {
+        let Some(last_move) = self.previous_fen_positions.pop() else {
+            return false;
+        };
+        self.game_done = false;
+        self.load_fen(&last_move);
+        true
+    }
+
+    fn restart(&mut self) {
+        let mut board = Board::init();
+        board.set_start_position();
+        self.white_turn = true;
+        self.previous_fen_positions = vec![];
+        self.board = board;
+        self.game_done = false;
+        self.en_passant = "-".to_string();
+        self.half_move_clock = 0i32;
+        self.full_move_number = 1i32;
+    }
+
+    /// Plays the specified move by calling the `play_move` method with the move's source and target squares.
+    /// Returns `true` if the move was played successfully, `false` otherwise.
+    fn play_move_ob(&mut self, chess_move: Move) -> bool {
+        self.play_move(chess_move, false)
+    }
+
+    fn play_move_from_string(
+        &mut self,
+        source_square: &str,
+        target_square: &str,
+        promotion_piece: &str,
+    ) -> bool {
+        let initial_position_byte = position_helper::try_letter_to_index(source_square).unwrap();
+        let final_position_byte = position_helper::try_letter_to_index(target_square).unwrap();
+        let _promotion = match promotion_piece {
+            "Q" => PIECE_BIT + WHITE_BIT + QUEEN,
+            "q" => PIECE_BIT + QUEEN,
+            _ => 0,
+        };
+        let mv = Move {
+            source: initial_position_byte,
+            target: final_position_byte,
+            promotion: _promotion,
+            flags: 0,
+        };
+        self.play_move(mv, false)
+    }
+
+    fn set_from_simple_fen(&mut self, fen: String) -> bool {
+        // A full FEN pasted here keeps its side to move, castling rights and clocks
+        let fen = fen.trim();
+        if fen.contains(char::is_whitespace) {
+            return self.try_set_from_fen(fen).is_ok();
+        }
+        if !Game::is_valid_piece_placement(fen) {
+            return false;
+        }
+
+        // Reset the board and the moves that led to the old position
+        self.board = Board::init();
+        self.previous_fen_positions.clear();
+
+        // Set the board state
+        let mut board_state_index = 0;
+        for c in fen.chars() {
+            if c == '/' {
+                continue;
+            }
+            if c.is_numeric() {
+                let num = c.to_digit(10).unwrap();
+                board_state_index += num;
+            } else {
+                // Set the piece
+                let Some(piece_type) = PieceType::from_char(c) else {
+                    panic!("This piece does not exist!");
+                };
+                let piece = piece_type.to_binary(c.is_uppercase());
+                let index: usize = board_state_index.try_into().unwrap();
+                self.board.state[index] = piece;
+                board_state_index += 1;
+            }
+        }
+        self.board.sync_bitboards();
+        self.board.recompute_material();
+
+        true
+    }
+
+    fn set_from_fen(&mut self, fen: String) {
+        // A new position starts a new game, so the old positions can no longer be undone
+        // back to or count towards a repetition
+        self.previous_fen_positions.clear();
+        self.load_fen(&fen);
+    }
+
+    fn get_fen(&self) -> String {
+        self.fen()
+    }
+
+    fn get_fen_simple(&self) -> String {
+        self.simple_fen()
+    }
+
+    fn play_move(&mut self, mv: Move, check_move_legality: bool) -> bool {
+        if self.game_done {
+            let _winning_side: String = if self.white_turn {
+                "Black".to_string()
+            } else {
+                "White".to_string()
+            };
+
+            return false;
+        }
+
+        // Get the piece at the source index
+        let piece_bits = self.board.state.get(mv.source as usize).unwrap_or(&0u8);
+
+        // bool for the half move clock
+        let mut piece_taken = false;
+
+        if piece_bits == &0u8 {
+            return false;
+        }
+
+        let piece = Piece::init_from_binary(*piece_bits);
+
+        // flags used to update the castling options
+        let king_moved = piece.class == PieceType::King;
+        let rook_moved = piece.class == PieceType::Rook;
+
+        // Check if turn is correct
+        if piece.is_white != self.white_turn {
+            return false;
+        }
+
+        if check_move_legality {
             let possible_moves = piece.possible_moves(mv.source, &self.board);
             // Early return if the move is not possible
             if !possible_moves.contains(&mv) {
@@ -472,6 +2100,25 @@ impl ChessGame for Game {
             }
         }
 
+        // A king moving two squares is castling, which also moves the rook next to it
+        let castling_rook = if piece.class == PieceType::King && mv.source.abs_diff(mv.target) == 2
+        {
+            let (rook_pos, rook_target) = if mv.target > mv.source {
+                let rook_pos = if piece.is_white { 63 } else { 7 };
+                (rook_pos, rook_pos - 2)
+            } else {
+                let rook_pos = if piece.is_white { 56 } else { 0 };
+                (rook_pos, rook_pos + 3)
+            };
+            // Unchecked moves can ask to castle without a rook; refuse instead of moving garbage
+            if self.board.state[rook_pos as usize] != PieceType::Rook.to_binary(piece.is_white) {
+                return false;
+            }
+            Some((rook_pos, rook_target))
+        } else {
+            None
+        };
+
         // Move must be pseudolegal
         // Update the previous positions vector
         let previous_fen = self.get_fen();
@@ -488,37 +2135,26 @@ impl ChessGame for Game {
 
             if taken_p.class == PieceType::Rook {
                 let is_kingside = position_helper::get_col(mv.target) == 7;
-                let rook_moved = false;
                 self.set_castling_options(is_kingside, king_moved, rook_moved, true);
             }
         }
 
         // Handle castling
         if piece.class == PieceType::King {
-            let difference = mv.target as i32 - mv.source as i32;
-            if difference.abs() == 2 {
-                let king_side = difference > 0;
-                if king_side {
-                    let rook_pos = if piece.is_white { 63 } else { 7 };
-                    let rook = Piece::init_from_binary(self.board.state[rook_pos as usize]);
-                    self.update_board_object(&rook, rook_pos, rook_pos - 2, false);
-                } else {
-                    let rook_pos = if piece.is_white { 56 } else { 0 };
-                    let rook = Piece::init_from_binary(self.board.state[rook_pos as usize]);
-                    self.update_board_object(&rook, rook_pos, rook_pos + 3, false);
-                }
+            if let Some((rook_pos, rook_target)) = castling_rook {
+                let rook = Piece::init_from_binary(self.board.state[rook_pos as usize]);
+                self.update_board_object(&rook, rook_pos, rook_target, false);
             }
             // set castling options
-            self.set_castling_options(true, true, true, false);
+            self.set_castling_options(true, king_moved, rook_moved, false);
         }
 
         // Set en passant flag
         let en_passant_set: bool = self.set_en_passant_flag(&piece, mv.source, mv.target);
 
         // Update castling options if rook is moved
-        if piece.class == PieceType::Rook {
+        if rook_moved {
             let is_kingside = position_helper::get_col(mv.source) == 7;
-            let rook_moved = true;
             self.set_castling_options(is_kingside, king_moved, rook_moved, false);
         }
 
@@ -537,10 +2173,16 @@ impl ChessGame for Game {
         } else {
             self.update_board_object(&piece, mv.source, mv.target, en_passant_set);
         }
+        self.board.sync_bitboards();
         self.previous_fen_positions.push(previous_fen);
 
         self.white_turn = !self.white_turn;
 
+        // Captures can leave too little material to mate, which ends the game as a draw
+        if piece_taken && self.is_insufficient_material() {
+            self.game_done = true;
+        }
+
         //update the half move clock
         if piece.class == PieceType::Pawn || piece_taken {
             self.half_move_clock = 0;
@@ -594,13 +2236,13 @@ impl Game {
                 } else {
                     self.board.castling &= 0b1111_1110;
                 }
-            }
-
-            // black turn
-            if is_kingside {
-                self.board.castling &= 0b1111_0111;
             } else {
-                self.board.castling &= 0b1111_1011;
+                // black turn
+                if is_kingside {
+                    self.board.castling &= 0b1111_0111;
+                } else {
+                    self.board.castling &= 0b1111_1011;
+                }
             }
             return;
         }
@@ -632,13 +2274,16 @@ impl Game {
             && self.board.en_passant != 0
             && target_idx == self.board.en_passant
         {
-            if piece.is_white {
-                let pawn_taken_pos = self.board.en_passant + ROW;
-                self.board.state[pawn_taken_pos as usize] = 0;
+            let pawn_taken_pos = if piece.is_white {
+                self.board.en_passant + ROW
             } else {
-                let pawn_taken_pos = self.board.en_passant - ROW;
-                self.board.state[pawn_taken_pos as usize] = 0;
+                self.board.en_passant - ROW
+            };
+            let pawn_taken = self.board.state[pawn_taken_pos as usize];
+            if pawn_taken != 0 {
+                self.board.remove_material(pawn_taken);
             }
+            self.board.state[pawn_taken_pos as usize] = 0;
         }
     }
 
@@ -647,13 +2292,24 @@ impl Game {
             self.board.en_passant = 0;
             self.en_passant = "-".to_string();
         }
+        // Keep the material totals in step: drop the captured piece and swap the pawn for
+        // the promoted piece
+        let captured = self.board.state[target as usize];
+        if captured != 0 {
+            self.board.remove_material(captured);
+        }
+        let moved = self.board.state[source as usize];
+        if moved != piece.binary {
+            self.board.remove_material(moved);
+            self.board.add_material(piece.binary);
+        }
         self.board.state[target as usize] = piece.binary;
         self.board.state[source as usize] = 0;
     }
 }
 
 pub mod position_helper {
-    use crate::{Board, WHITE_BIT};
+    use crate::{Board, SquareError, WHITE_BIT};
 
     pub fn index_to_letter(index: u8) -> String {
         let row_selector: u8 = 0b00111000;
@@ -672,6 +2328,14 @@ pub mod position_helper {
         return_string
     }
 
+    /// Converts a square name such as "e4" to its board index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `letters` is not a square name; see `try_letter_to_index`.
+    #[deprecated(
+        note = "use `try_letter_to_index`, which reports a bad square name instead of panicking"
+    )]
     pub fn letter_to_index(letters: String) -> u8 {
         let mut letters_copy = letters;
         let num_char = letters_copy.pop().unwrap();
@@ -681,6 +2345,15 @@ pub mod position_helper {
         (row << 3) | col
     }
 
+    /// Converts a square name such as "e4" to its board index, or returns an error if it is
+    /// not a file from a to h followed by a rank from 1 to 8.
+    pub fn try_letter_to_index(letters: &str) -> Result<u8, SquareError> {
+        match letters.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(((b'8' - rank) << 3) | (file - b'a')),
+            _ => Err(SquareError::InvalidSquare(letters.to_string())),
+        }
+    }
+
     pub fn get_row(byte: u8) -> u8 {
         let row_selector: u8 = 0b00111000;
         (row_selector & byte) >> 3
@@ -727,108 +2400,1032 @@ pub mod position_helper {
 }
 
 pub mod engine {
-    use std::collections::hash_map::DefaultHasher;
     use std::collections::HashMap;
-    use std::hash::Hash;
-    use std::hash::Hasher;
-    use std::time::Instant;
+    use std::fmt;
+    use std::time::{Duration, Instant};
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
+    use crate::constants::{FIFTY_MOVE_RULE, SEVENTY_FIVE_MOVE_RULE, TOTAL_PHASE};
+    use crate::kpk;
     use crate::position_helper;
     use crate::psqt;
     use crate::Board;
     use crate::ChessGame;
+    use crate::FenError;
     use crate::Game;
+    use crate::GameStatus;
     use crate::Move;
     use crate::{BasicPiece, Piece, PieceType};
 
+    /// Search depth used when the engine has not been configured otherwise.
+    pub const DEFAULT_SEARCH_DEPTH: u8 = 2;
+
+    /// Longest line, in plies, the main search may look ahead before quiescence. Tables
+    /// indexed by ply can be sized from it.
+    pub const MAX_PLY: usize = 64;
+
+    /// Deepest search, in moves per side, that stays within `MAX_PLY`. Deeper requests are
+    /// clamped to it.
+    pub const MAX_SEARCH_DEPTH: u8 = (MAX_PLY / 2) as u8;
+
+    /// Search depth for each difficulty level, starting at level 1.
+    const DIFFICULTY_DEPTHS: [u8; 5] = [1, 1, 2, 2, 3];
+
+    /// Skill level for each difficulty level, starting at level 1.
+    const DIFFICULTY_SKILLS: [u8; 5] = [2, 5, 7, 9, MAX_SKILL_LEVEL];
+
+    /// Skill level at which the engine always plays the best move it finds.
+    pub const MAX_SKILL_LEVEL: u8 = 10;
+
+    /// Score margin, in centipawns, added to the root move selection for each skill level below the maximum.
+    /// At skill 0 the engine picks among moves up to 250 centipawns worse than the best, which is still
+    /// less than a minor piece, so weakened play never hangs a piece outright.
+    const SKILL_MARGIN_STEP: i32 = 25;
+
+    /// Penalty, in centipawns, for a castled king boxed in on its back rank, for each enemy
+    /// rook or queen that could deliver mate along it.
+    const BACK_RANK_PENALTY: i32 = 30;
+
+    /// Bonus, in centipawns, for each rook or queen on the opponent's second rank, with the
+    /// middlegame and endgame values blended by game phase like the king tables.
+    const SEVENTH_RANK_BONUS_EARLY: i32 = 15;
+    const SEVENTH_RANK_BONUS_LATE: i32 = 25;
+
+    /// Half move clock from which scores shrink towards a draw, reaching 0 at 100, where the
+    /// losing side can claim a draw under the fifty-move rule.
+    const FIFTY_MOVE_SCALE_START: i32 = 80;
+
+    /// Bitboard masks of the 7th rank, where white pieces attack black's pawns, and of the
+    /// 2nd rank, its counterpart for black.
+    const RANK_7: u64 = 0x00FF_0000_0000_0000;
+    const RANK_2: u64 = 0x0000_0000_0000_FF00;
+
+    /// Penalty, in centipawns, for a knight on the a- or h-file, where it covers half as
+    /// many squares as in the centre.
+    const KNIGHT_ON_RIM_PENALTY: i32 = 15;
+
+    /// Bonus, in centipawns, for a knight on an outpost in the middlegame. It halves by the
+    /// endgame, when there are fewer pieces for the knight to harass.
+    const KNIGHT_OUTPOST_BONUS: i32 = 25;
+
+    /// Bitboard mask of the a-file; shifted left by n it gives the n-th file.
+    const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+    /// Bonus, in centipawns, for a passed pawn by its rank counted from its own side, and
+    /// the extra bonus when it is connected to another passer on a neighbouring file.
+    const PASSED_PAWN_BONUS: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+    const CONNECTED_PASSER_BONUS: [i32; 8] = [0, 5, 10, 15, 25, 40, 65, 0];
+
+    /// A knight, bishop, rook or queen that is attacked and has no safe square to move to is
+    /// likely to be lost, so it gives up this fraction of its value: half.
+    const TRAPPED_PIECE_DIVISOR: i32 = 2;
+
+    /// Number of evaluations the cache holds. It is emptied when it fills up, so memory
+    /// stays bounded over a long game.
+    pub const EVAL_CACHE_SIZE: usize = 1 << 20;
+
+    /// Number of pawn structures whose score is cached. Far fewer pawn structures than
+    /// positions come up in a search, so the pawn cache is much smaller.
+    pub const PAWN_CACHE_SIZE: usize = 1 << 14;
+
+    /// Number of positions whose best move is remembered for move ordering. Like the
+    /// evaluation cache, the table is emptied when it fills up.
+    pub const HASH_MOVE_TABLE_SIZE: usize = 1 << 20;
+
+    /// Margins, in centipawns, by remaining depth, for futility pruning: at depth 1 and 2 a
+    /// move whose static score plus its margin cannot reach alpha is not searched. Depth 2
+    /// leaves the opponent a reply, so its margin is wider.
+    const FUTILITY_MARGINS: [i32; 3] = [0, 200, 500];
+
+    /// Material, in centipawns, a chosen move may leave to the opponent by static exchange
+    /// evaluation before the blunder check searches for another one.
+    pub const BLUNDER_MARGIN: i32 = 300;
+
+    /// Mop-up weights, in centipawns, used once one side is down to a bare king against at
+    /// least a rook: per step the lone king stands from the centre, and per step the
+    /// attacking king closes in on it.
+    const MOP_UP_CENTER_WEIGHT: i32 = 30;
+    const MOP_UP_KING_DISTANCE_WEIGHT: i32 = 10;
+
+    /// Largest centipawn loss, against the best move, of a good move, an inaccuracy and a
+    /// mistake. Anything worse is a blunder.
+    pub const GOOD_MOVE_LOSS: i32 = 50;
+    pub const INACCURACY_LOSS: i32 = 100;
+    pub const MISTAKE_LOSS: i32 = 300;
+
+    /// Playing style of the engine, which picks the piece-square tables used by `evaluate`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Personality {
+        /// The default style, which pushes pawns and pieces forward.
+        #[default]
+        Aggressive,
+        /// A quieter style that develops pieces before advancing pawns.
+        Positional,
+    }
+
+    impl Personality {
+        /// Returns the piece-square tables of the personality.
+        pub fn tables(&self) -> &'static psqt::Tables {
+            match self {
+                Personality::Aggressive => &psqt::AGGRESSIVE,
+                Personality::Positional => &psqt::POSITIONAL,
+            }
+        }
+
+        /// Parses a personality from its lowercase name, as sent by the frontend.
+        pub fn from_name(name: &str) -> Option<Personality> {
+            match name {
+                "aggressive" => Some(Personality::Aggressive),
+                "positional" => Some(Personality::Positional),
+                _ => None,
+            }
+        }
+    }
+
+    /// How good a played move was compared with the engine's best move, as returned by
+    /// `Engine::classify_move`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum MoveQuality {
+        /// As good as the engine's best move.
+        Best,
+        /// Loses at most `GOOD_MOVE_LOSS` centipawns.
+        Good,
+        /// Loses at most `INACCURACY_LOSS` centipawns.
+        Inaccuracy,
+        /// Loses at most `MISTAKE_LOSS` centipawns.
+        Mistake,
+        /// Loses more than `MISTAKE_LOSS` centipawns.
+        Blunder,
+    }
+
+    /// Outcome of a search, as returned by `Engine::search`.
+    #[derive(Debug, Clone)]
+    pub struct SearchResult {
+        /// The move chosen, or `None` if the side to move has no legal moves.
+        pub best_move: Option<Move>,
+        /// Score of the best move in centipawns, from the point of view of the side to move.
+        pub score: i32,
+        /// The depth that was searched, in moves per side.
+        pub depth: u8,
+        /// Number of positions visited.
+        pub nodes: u64,
+        /// The line the engine expects, starting with the best move.
+        pub pv: Vec<Move>,
+        /// Time the search took.
+        pub elapsed: Duration,
+        /// How full the evaluation cache was at the end, in parts per thousand. The engine has
+        /// no transposition table, so this is what it reports as UCI `hashfull`.
+        pub hashfull: u16,
+    }
+
+    impl SearchResult {
+        /// Returns the nodes searched per second.
+        pub fn nps(&self) -> u64 {
+            let micros = self.elapsed.as_micros().max(1);
+            (self.nodes as u128 * 1_000_000 / micros) as u64
+        }
+
+        /// Returns the number of moves to mate when the score is a mate score, positive when
+        /// the side to move mates and negative when it gets mated.
+        pub fn mate_in(&self) -> Option<i32> {
+            let ply = 100000 - self.score.abs();
+            if ply > MAX_PLY as i32 {
+                return None;
+            }
+            // The mating side plays the first and last moves of the line
+            Some(if self.score > 0 {
+                (ply + 1) / 2
+            } else {
+                -ply / 2
+            })
+        }
+
+        /// Returns the search as a UCI `info` line, e.g.
+        /// `info depth 2 score cp 15 nodes 1445 nps 131000 hashfull 1 time 11 pv d2d4 d7d5`.
+        /// Mate scores are given as `score mate 3` instead, and `hashfull` is the fill of the
+        /// evaluation cache.
+        pub fn info_line(&self) -> String {
+            let pv: Vec<String> = self.pv.iter().map(Move::to_uci).collect();
+            let score = match self.mate_in() {
+                Some(moves) => format!("mate {}", moves),
+                None => format!("cp {}", self.score),
+            };
+            format!(
+                "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+                self.depth,
+                score,
+                self.nodes,
+                self.nps(),
+                self.hashfull,
+                self.elapsed.as_millis(),
+                pv.join(" ")
+            )
+        }
+    }
+
+    /// The terms of the static evaluation, each in centipawns from white's point of view,
+    /// as returned by `Engine::evaluate_verbose`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct EvalBreakdown {
+        /// Value of the pieces on the board.
+        pub material: i32,
+        /// Piece-square table bonuses of the current personality, the king's blended by game phase.
+        pub piece_squares: i32,
+        /// Rooks and queens on the seventh rank.
+        pub seventh_rank: i32,
+        /// Knights on the rim and on outposts.
+        pub knights: i32,
+        /// Passed and connected passed pawns.
+        pub pawn_structure: i32,
+        /// Driving a bare king to the edge and closing in on it.
+        pub mop_up: i32,
+        /// Pieces that are attacked and have no safe square to go to.
+        pub trapped_pieces: i32,
+        /// Castled kings without luft while heavy pieces remain.
+        pub back_rank: i32,
+    }
+
+    impl EvalBreakdown {
+        /// Returns the sum of the terms, which is the score `Engine::evaluate` gives.
+        pub fn total(&self) -> i32 {
+            self.material
+                + self.piece_squares
+                + self.seventh_rank
+                + self.knights
+                + self.pawn_structure
+                + self.mop_up
+                + self.trapped_pieces
+                + self.back_rank
+        }
+    }
+
+    impl fmt::Display for EvalBreakdown {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let terms = [
+                ("material", self.material),
+                ("piece squares", self.piece_squares),
+                ("seventh rank", self.seventh_rank),
+                ("knights", self.knights),
+                ("pawn structure", self.pawn_structure),
+                ("mop-up", self.mop_up),
+                ("trapped pieces", self.trapped_pieces),
+                ("back rank", self.back_rank),
+            ];
+            for (name, score) in terms {
+                writeln!(f, "{:<15}{:>7}", name, score)?;
+            }
+            write!(f, "{:<15}{:>7}", "total", self.total())
+        }
+    }
+
     pub struct Engine {
         pub game: Game,
         pub positions_evaluated: HashMap<u64, i32>,
+        /// Scores of the terms that only depend on the pawns, keyed by `Board::pawn_hash`.
+        pawn_cache: HashMap<u64, i32>,
         num_positions_evaluated: i64,
         cache_hits_last_eval: i64,
+        search_depth: u8,
+        skill_level: u8,
+        rng: StdRng,
+        iterations: Vec<(u8, Duration, u64)>,
+        personality: Personality,
+        /// Best line found below each ply of the current search, indexed by ply from the root.
+        pv_table: Vec<Vec<Move>>,
+        /// Length of the game history when the current search started.
+        root_ply: usize,
+        /// Best move found at each searched position, keyed by `Game::hash`. It is kept
+        /// between searches and tried first when the position is searched again.
+        hash_moves: HashMap<u64, Move>,
+        hash_move_ordering: bool,
+        verbose: bool,
+        blunder_check: bool,
+        futility_pruning: bool,
+    }
+
+    /// Builds an `Engine` with non-default settings in a single expression, e.g.
+    /// `EngineBuilder::new().depth(3).skill_level(5).seed(7).build()`.
+    /// Settings that are not given keep the defaults of `Engine::init`.
+    #[derive(Default)]
+    pub struct EngineBuilder {
+        game: Option<Game>,
+        depth: Option<u8>,
+        skill_level: Option<u8>,
+        seed: Option<u64>,
+        personality: Option<Personality>,
+        verbose: bool,
+        blunder_check: bool,
+    }
+
+    impl EngineBuilder {
+        pub fn new() -> EngineBuilder {
+            EngineBuilder::default()
+        }
+
+        /// Starts the engine from the given game instead of the initial position.
+        pub fn game(mut self, game: Game) -> EngineBuilder {
+            self.game = Some(game);
+            self
+        }
+
+        /// Sets the depth used by `search_default`. See `Engine::set_search_depth`.
+        pub fn depth(mut self, depth: u8) -> EngineBuilder {
+            self.depth = Some(depth);
+            self
+        }
+
+        /// Sets the skill level. See `Engine::set_skill_level`.
+        pub fn skill_level(mut self, level: u8) -> EngineBuilder {
+            self.skill_level = Some(level);
+            self
+        }
+
+        /// Seeds the random number generator used by weakened play. See `Engine::set_seed`.
+        pub fn seed(mut self, seed: u64) -> EngineBuilder {
+            self.seed = Some(seed);
+            self
+        }
+
+        /// Sets the playing style. See `Engine::set_personality`.
+        pub fn personality(mut self, personality: Personality) -> EngineBuilder {
+            self.personality = Some(personality);
+            self
+        }
+
+        /// Prints a summary of every search. See `Engine::set_verbose`.
+        pub fn verbose(mut self, verbose: bool) -> EngineBuilder {
+            self.verbose = verbose;
+            self
+        }
+
+        /// Re-searches moves that hang material. See `Engine::set_blunder_check`.
+        pub fn blunder_check(mut self, enabled: bool) -> EngineBuilder {
+            self.blunder_check = enabled;
+            self
+        }
+
+        pub fn build(self) -> Engine {
+            let mut engine = Engine::init_from_game(self.game.unwrap_or_else(Game::init));
+            if let Some(depth) = self.depth {
+                engine.set_search_depth(depth);
+            }
+            if let Some(level) = self.skill_level {
+                engine.set_skill_level(level);
+            }
+            if let Some(seed) = self.seed {
+                engine.set_seed(seed);
+            }
+            if let Some(personality) = self.personality {
+                engine.set_personality(personality);
+            }
+            engine.set_verbose(self.verbose);
+            engine.set_blunder_check(self.blunder_check);
+            engine
+        }
     }
 
     impl Engine {
         pub fn init() -> Engine {
+            Engine::init_from_game(Game::init())
+        }
+
+        pub fn init_from_game(game: Game) -> Engine {
             Engine {
-                game: Game::init(),
+                game,
                 positions_evaluated: HashMap::new(),
+                pawn_cache: HashMap::new(),
                 num_positions_evaluated: 0,
                 cache_hits_last_eval: 0,
+                search_depth: DEFAULT_SEARCH_DEPTH,
+                skill_level: MAX_SKILL_LEVEL,
+                rng: StdRng::from_entropy(),
+                iterations: vec![],
+                personality: Personality::default(),
+                pv_table: vec![],
+                root_ply: 0,
+                hash_moves: HashMap::new(),
+                hash_move_ordering: true,
+                verbose: false,
+                blunder_check: false,
+                futility_pruning: true,
+            }
+        }
+
+        /// Returns the depth used by `search_default` and `get_best_move_default`.
+        pub fn search_depth(&self) -> u8 {
+            self.search_depth
+        }
+
+        /// Sets the depth used by `search_default` and `get_best_move_default`. A depth of 0
+        /// is raised to 1, and one above `MAX_SEARCH_DEPTH` is lowered to it.
+        pub fn set_search_depth(&mut self, depth: u8) {
+            self.search_depth = depth.clamp(1, MAX_SEARCH_DEPTH);
+        }
+
+        /// Configures the engine strength from a difficulty level between 1 (easiest) and 5 (hardest).
+        /// Levels outside that range are clamped.
+        pub fn set_difficulty(&mut self, level: u8) {
+            let level = level.clamp(1, DIFFICULTY_DEPTHS.len() as u8);
+            self.set_search_depth(DIFFICULTY_DEPTHS[level as usize - 1]);
+            self.set_skill_level(DIFFICULTY_SKILLS[level as usize - 1]);
+        }
+
+        /// Returns the skill level, between 0 and `MAX_SKILL_LEVEL`.
+        pub fn skill_level(&self) -> u8 {
+            self.skill_level
+        }
+
+        /// Sets the skill level. Below `MAX_SKILL_LEVEL` the engine picks a random root move
+        /// among those scoring close to the best one. Levels above the maximum are clamped.
+        pub fn set_skill_level(&mut self, level: u8) {
+            self.skill_level = level.min(MAX_SKILL_LEVEL);
+        }
+
+        /// Reseeds the random number generator used by weakened play, making move choices reproducible.
+        pub fn set_seed(&mut self, seed: u64) {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+
+        /// Returns the playing style.
+        pub fn personality(&self) -> Personality {
+            self.personality
+        }
+
+        /// Sets the playing style. Cached evaluations are dropped since they were made with
+        /// the previous tables.
+        pub fn set_personality(&mut self, personality: Personality) {
+            if personality != self.personality {
+                self.positions_evaluated.clear();
+            }
+            self.personality = personality;
+        }
+
+        /// Turns printing a summary of every search to stdout on or off. The engine is quiet
+        /// by default, so it can be used as a library or behind a protocol that owns stdout.
+        pub fn set_verbose(&mut self, verbose: bool) {
+            self.verbose = verbose;
+        }
+
+        /// Turns the blunder check on or off. While it is on, `search` makes sure the move it
+        /// picks does not hang `BLUNDER_MARGIN` or more of material by static exchange
+        /// evaluation, as a safety net for what lies past the search horizon. It is off by
+        /// default.
+        pub fn set_blunder_check(&mut self, enabled: bool) {
+            self.blunder_check = enabled;
+        }
+
+        /// Turns trying the stored best move of a position first on or off. It is on by
+        /// default; best moves are still recorded while it is off.
+        pub fn set_hash_move_ordering(&mut self, enabled: bool) {
+            self.hash_move_ordering = enabled;
+        }
+
+        /// Turns futility pruning on or off. It is on by default; see `FUTILITY_MARGINS`.
+        pub fn set_futility_pruning(&mut self, enabled: bool) {
+            self.futility_pruning = enabled;
+        }
+
+        /// Moves the best move stored for the current position, if any, to the front.
+        fn order_hash_move_first(&self, moves: &mut [Move]) {
+            if !self.hash_move_ordering {
+                return;
+            }
+            let Some(hash_move) = self.hash_moves.get(&self.game.hash()) else {
+                return;
+            };
+            if let Some(index) = moves.iter().position(|mv| mv == hash_move) {
+                moves[..=index].rotate_right(1);
+            }
+        }
+
+        /// Remembers `mv` as the best move of the current position.
+        fn store_hash_move(&mut self, mv: Move) {
+            if self.hash_moves.len() >= HASH_MOVE_TABLE_SIZE {
+                self.hash_moves.clear();
+            }
+            self.hash_moves.insert(self.game.hash(), mv);
+        }
+
+        /// Returns the number of positions visited by the last search.
+        pub fn nodes_searched(&self) -> i64 {
+            self.num_positions_evaluated
+        }
+
+        /// Returns the share of evaluations in the last search that were answered from the
+        /// cache, between 0 and 1. Returns 0 if the search visited no positions.
+        pub fn cache_hit_rate(&self) -> f32 {
+            if self.num_positions_evaluated == 0 {
+                return 0f32;
+            }
+            self.cache_hits_last_eval as f32 / self.num_positions_evaluated as f32
+        }
+
+        /// Returns how full the evaluation cache is, in parts per thousand of
+        /// `EVAL_CACHE_SIZE`. The engine has no transposition table, so this is what it
+        /// reports as UCI `hashfull`.
+        pub fn hashfull(&self) -> u16 {
+            (self.positions_evaluated.len() * 1000 / EVAL_CACHE_SIZE) as u16
+        }
+
+        /// Returns the depth, elapsed time and node count of each iteration completed by the
+        /// last call to `search_iterative` or `get_best_move_iterative`, shallowest first.
+        pub fn search_iterations(&self) -> &[(u8, Duration, u64)] {
+            &self.iterations
+        }
+
+        /// Searches at depth 1, then 2, and so on up to `depth`, recording the time and nodes
+        /// of every iteration. Returns the result of the deepest one, with the nodes and time
+        /// of all iterations added up. The depth is clamped like in `search`, and a depth of 0
+        /// searches depth 1.
+        pub fn search_iterative(&mut self, depth: u8) -> SearchResult {
+            let depth = depth.clamp(1, MAX_SEARCH_DEPTH);
+            self.iterations.clear();
+            let mut deepest: Option<SearchResult> = None;
+            for current_depth in 1..=depth {
+                let mut result = self.search(current_depth);
+                self.iterations
+                    .push((current_depth, result.elapsed, result.nodes));
+                if let Some(previous) = deepest {
+                    result.nodes += previous.nodes;
+                    result.elapsed += previous.elapsed;
+                }
+                deepest = Some(result);
             }
+            deepest.expect("at least one iteration is searched")
         }
 
-        pub fn init_from_game(game: Game) -> Engine {
-            Engine {
-                game,
-                positions_evaluated: HashMap::new(),
-                num_positions_evaluated: 0,
-                cache_hits_last_eval: 0,
+        /// Searches like `search_iterative` and returns the move found by the deepest
+        /// iteration, or a move from a8 to a8 if there is no legal move.
+        pub fn get_best_move_iterative(&mut self, depth: u8) -> Move {
+            self.search_iterative(depth).best_move.unwrap_or(Move {
+                source: 0,
+                target: 0,
+                promotion: 0,
+                flags: 0,
+            })
+        }
+
+        /// Searches like `search` using the configured search depth.
+        pub fn search_default(&mut self) -> SearchResult {
+            self.search(self.search_depth)
+        }
+
+        /// Searches for the best move using the configured search depth.
+        pub fn get_best_move_default(&mut self) -> Move {
+            self.get_best_move(self.search_depth)
+        }
+
+        /// Returns the static evaluation of the engine's current position, in centipawns,
+        /// from the point of view of the side to move: positive when the side to move stands
+        /// better, whichever color it is. `evaluate` gives the same score from white's
+        /// point of view, positive when white is better.
+        pub fn evaluate_relative(&mut self) -> i32 {
+            let score = self.evaluate(&self.game.board.clone());
+            if self.game.white_turn {
+                score
+            } else {
+                -score
             }
         }
 
         pub fn evaluate(&mut self, board: &Board) -> i32 {
+            // Two bare kings are a dead draw whatever the placement
+            if board.material == [0, 0] {
+                return 0;
+            }
+
             // early return from hashed positions eval
-            let mut hasher = DefaultHasher::new();
-            board.hash(&mut hasher);
-            let board_hash = hasher.finish();
+            let board_hash = board.zobrist_hash();
             if self.positions_evaluated.contains_key(&board_hash) {
                 self.cache_hits_last_eval += 1;
                 return self.positions_evaluated[&board_hash];
             }
 
-            let mut score = 0;
+            let pawn_structure = self.pawn_structure(board);
+            let score = self.evaluate_terms(board, pawn_structure).total();
+
+            if self.positions_evaluated.len() >= EVAL_CACHE_SIZE {
+                self.positions_evaluated.clear();
+            }
+            self.positions_evaluated.insert(board_hash, score);
+
+            score
+        }
+
+        /// Returns the static evaluation of `board` split into its terms, which add up to
+        /// `evaluate`. Unlike `evaluate` it neither reads nor fills the caches, so it can be
+        /// called on a shared engine to see why a position scores as it does.
+        pub fn evaluate_verbose(&self, board: &Board) -> EvalBreakdown {
+            if board.material == [0, 0] {
+                return EvalBreakdown::default();
+            }
+            self.evaluate_terms(board, Engine::pawn_structure_score(board))
+        }
 
-            // TODO: check for middle game and end game
+        /// Computes every evaluation term but the pawn structure, which is passed in so
+        /// `evaluate` can take it from the pawn cache.
+        fn evaluate_terms(&self, board: &Board, pawn_structure: i32) -> EvalBreakdown {
+            // Material comes from the running totals kept on the board
+            let material = board.material[0] - board.material[1];
 
-            // Material
+            // The king tables are blended by game phase, from the middlegame table with all
+            // pieces on the board to the endgame table once they have been traded off
+            let phase = board.game_phase() as i32;
+
+            // Piece placement, the tables include the piece value so it is taken back out
+            let tables = self.personality.tables();
+            let mut piece_squares = 0;
             for i in 0..64 {
                 let piece = board.state[i];
                 if piece == 0 {
                     continue;
                 }
                 let piece: Piece = Piece::init_from_binary(piece);
-                let position_value = {
-                    if piece.is_white {
-                        match piece.class {
-                            PieceType::King => 10000 + psqt::KING[i],
-                            PieceType::Queen => psqt::QUEEN[i],
-                            PieceType::Rook => psqt::ROOK[i],
-                            PieceType::Bishop => psqt::BISHOP[i],
-                            PieceType::Knight => psqt::KNIGHT[i],
-                            PieceType::Pawn => psqt::PAWN[i],
-                        }
-                    } else {
-                        match piece.class {
-                            PieceType::King => 10000 + psqt::KING[psqt::FLIP[i]],
-                            PieceType::Queen => psqt::QUEEN[psqt::FLIP[i]],
-                            PieceType::Rook => psqt::ROOK[psqt::FLIP[i]],
-                            PieceType::Bishop => psqt::BISHOP[psqt::FLIP[i]],
-                            PieceType::Knight => psqt::KNIGHT[psqt::FLIP[i]],
-                            PieceType::Pawn => psqt::PAWN[psqt::FLIP[i]],
-                        }
+                let square = if piece.is_white { i } else { psqt::FLIP[i] };
+                let position_value = match piece.class {
+                    PieceType::King => {
+                        let king = tables.king[square] * phase
+                            + tables.king_late[square] * (TOTAL_PHASE - phase);
+                        PieceType::King.value() + king / TOTAL_PHASE
                     }
+                    PieceType::Queen => tables.queen[square] - piece.class.value(),
+                    PieceType::Rook => tables.rook[square] - piece.class.value(),
+                    PieceType::Bishop => tables.bishop[square] - piece.class.value(),
+                    PieceType::Knight => tables.knight[square] - piece.class.value(),
+                    PieceType::Pawn => tables.pawn[square] - piece.class.value(),
                 };
                 if piece.is_white {
-                    score += position_value;
+                    piece_squares += position_value;
                 } else {
-                    score -= position_value;
+                    piece_squares -= position_value;
+                }
+            }
+
+            // Rooks and queens on the 7th rank tie the enemy king and pawns down
+            let seventh_rank_bonus = (SEVENTH_RANK_BONUS_EARLY * phase
+                + SEVENTH_RANK_BONUS_LATE * (TOTAL_PHASE - phase))
+                / TOTAL_PHASE;
+            let white_on_seventh = ((board.bitboard[1] | board.bitboard[4]) & RANK_7).count_ones();
+            let black_on_second = ((board.bitboard[7] | board.bitboard[10]) & RANK_2).count_ones();
+            let seventh_rank =
+                seventh_rank_bonus * (white_on_seventh as i32 - black_on_second as i32);
+
+            let knights = Engine::knight_placement(board, true, phase)
+                - Engine::knight_placement(board, false, phase);
+
+            let white_attacks = Engine::attack_map(board, true);
+            let black_attacks = Engine::attack_map(board, false);
+            let trapped_pieces = Engine::trapped_pieces(board, false, white_attacks)
+                - Engine::trapped_pieces(board, true, black_attacks);
+
+            // A boxed-in king is only a tactical risk while heavy pieces are still around
+            let back_rank = if phase > TOTAL_PHASE / 2 {
+                Engine::back_rank_penalty(board, false) - Engine::back_rank_penalty(board, true)
+            } else {
+                0
+            };
+
+            EvalBreakdown {
+                material,
+                piece_squares,
+                seventh_rank,
+                knights,
+                pawn_structure,
+                mop_up: Engine::mop_up(board),
+                trapped_pieces,
+                back_rank,
+            }
+        }
+
+        /// Returns the rim penalties and outpost bonuses of the knights of the given color.
+        ///
+        /// An outpost is a square in the opponent's half that is defended by a friendly pawn
+        /// and can never be attacked by an enemy pawn, because there are none left on the
+        /// neighbouring files in front of it.
+        fn knight_placement(board: &Board, is_white: bool, phase: i32) -> i32 {
+            let (mut knights, own_pawns, enemy_pawns) = if is_white {
+                (board.bitboard[2], board.bitboard[0], board.bitboard[6])
+            } else {
+                (board.bitboard[8], board.bitboard[6], board.bitboard[0])
+            };
+            let outpost_bonus = KNIGHT_OUTPOST_BONUS * (TOTAL_PHASE + phase) / (2 * TOTAL_PHASE);
+
+            let mut score = 0;
+            while knights != 0 {
+                let bit = knights.trailing_zeros();
+                knights &= knights - 1;
+                let rank = bit / 8;
+                let file = bit % 8;
+                if file == 0 || file == 7 {
+                    score -= KNIGHT_ON_RIM_PENALTY;
+                }
+
+                let relative_rank = if is_white { rank } else { 7 - rank };
+                if relative_rank < 4 {
+                    continue;
+                }
+                let adjacent_files = Engine::adjacent_files(file);
+                // Pawns defend from the rank behind and attack from every rank in front
+                let defender_rank = if is_white { rank - 1 } else { rank + 1 };
+                let ranks_ahead = Engine::ranks_ahead(rank, is_white);
+                let defended = own_pawns & adjacent_files & (0xFF << (defender_rank * 8)) != 0;
+                let attackable = enemy_pawns & adjacent_files & ranks_ahead != 0;
+                if defended && !attackable {
+                    score += outpost_bonus;
+                }
+            }
+            score
+        }
+
+        /// Returns the score of the terms that only depend on the pawns, positive when they
+        /// favour white. Scores are cached by pawn structure, so positions that only differ
+        /// in their other pieces share the work.
+        pub fn pawn_structure(&mut self, board: &Board) -> i32 {
+            let pawn_hash = board.pawn_hash();
+            if let Some(&score) = self.pawn_cache.get(&pawn_hash) {
+                return score;
+            }
+            let score = Engine::pawn_structure_score(board);
+            if self.pawn_cache.len() >= PAWN_CACHE_SIZE {
+                self.pawn_cache.clear();
+            }
+            self.pawn_cache.insert(pawn_hash, score);
+            score
+        }
+
+        /// Scores the pawn structure of `board` without the cache.
+        fn pawn_structure_score(board: &Board) -> i32 {
+            Engine::passed_pawns(board, true) - Engine::passed_pawns(board, false)
+        }
+
+        /// Returns the number of pawn structures whose score is cached.
+        pub fn pawn_cache_len(&self) -> usize {
+            self.pawn_cache.len()
+        }
+
+        /// Returns the passed pawn bonus for the given color. A pawn is passed when no enemy
+        /// pawn stands in front of it on its own or a neighbouring file, and it earns more the
+        /// further it has advanced, plus an extra bonus when another passer stands on a
+        /// neighbouring file to support it.
+        fn passed_pawns(board: &Board, is_white: bool) -> i32 {
+            let (own_pawns, enemy_pawns) = if is_white {
+                (board.bitboard[0], board.bitboard[6])
+            } else {
+                (board.bitboard[6], board.bitboard[0])
+            };
+
+            let mut passers = 0u64;
+            let mut pawns = own_pawns;
+            while pawns != 0 {
+                let bit = pawns.trailing_zeros();
+                pawns &= pawns - 1;
+                let files = Engine::adjacent_files(bit % 8) | (FILE_A << (bit % 8));
+                if enemy_pawns & files & Engine::ranks_ahead(bit / 8, is_white) == 0 {
+                    passers |= 1 << bit;
                 }
             }
-            self.positions_evaluated.insert(board_hash, score);
 
+            let mut score = 0;
+            let mut remaining = passers;
+            while remaining != 0 {
+                let bit = remaining.trailing_zeros();
+                remaining &= remaining - 1;
+                let rank = (bit / 8) as usize;
+                let relative_rank = if is_white { rank } else { 7 - rank };
+                score += PASSED_PAWN_BONUS[relative_rank];
+                if passers & Engine::adjacent_files(bit % 8) != 0 {
+                    score += CONNECTED_PASSER_BONUS[relative_rank];
+                }
+            }
             score
         }
 
+        /// Returns a bitboard mask of the files on either side of `file`.
+        fn adjacent_files(file: u32) -> u64 {
+            let mut files = 0u64;
+            if file > 0 {
+                files |= FILE_A << (file - 1);
+            }
+            if file < 7 {
+                files |= FILE_A << (file + 1);
+            }
+            files
+        }
+
+        /// Returns a bitboard mask of every rank in front of `rank` for the given color.
+        fn ranks_ahead(rank: u32, is_white: bool) -> u64 {
+            if is_white {
+                u64::MAX.checked_shl((rank + 1) * 8).unwrap_or(0)
+            } else {
+                (1u64 << (rank * 8)) - 1
+            }
+        }
+
+        /// Returns the mop-up bonus, from white's point of view, when one side has a bare king
+        /// and the other at least a rook's worth of material. Pushing the lone king to the
+        /// edge and bringing the other king close is what the basic mates need, and neither
+        /// shows up in material or the piece tables.
+        fn mop_up(board: &Board) -> i32 {
+            let strong_is_white = match board.material {
+                [white, 0] if white >= PieceType::Rook.value() => true,
+                [0, black] if black >= PieceType::Rook.value() => false,
+                _ => return 0,
+            };
+            let (Some(strong_king), Some(lone_king)) = (
+                board.king_square(strong_is_white),
+                board.king_square(!strong_is_white),
+            ) else {
+                return 0;
+            };
+            let (row, col) = (
+                position_helper::get_row(lone_king),
+                position_helper::get_col(lone_king),
+            );
+            // Steps from the four centre squares, 0 in the centre and 6 in a corner
+            let center_distance = row.max(7 - row) + col.max(7 - col) - 8;
+            let king_distance = position_helper::get_row(strong_king)
+                .abs_diff(row)
+                .max(position_helper::get_col(strong_king).abs_diff(col));
+            let score = MOP_UP_CENTER_WEIGHT * center_distance as i32
+                + MOP_UP_KING_DISTANCE_WEIGHT * (7 - king_distance as i32);
+            if strong_is_white {
+                score
+            } else {
+                -score
+            }
+        }
+
+        /// Returns the squares attacked by the pieces of the given color, as a mask with one
+        /// bit per board state index.
+        fn attack_map(board: &Board, is_white: bool) -> u64 {
+            let mut attacks = 0u64;
+            for square in 0..64u8 {
+                if board.piece_color(square) != Some(is_white) {
+                    continue;
+                }
+                let piece = Piece::init_from_binary(board.state[square as usize]);
+                for target in piece.attacked_squares(square, board) {
+                    attacks |= 1u64 << target;
+                }
+            }
+            attacks
+        }
+
+        /// Returns the penalty for the trapped pieces of the given color: knights, bishops,
+        /// rooks and queens that `enemy_attacks` hits and that cannot step or capture onto any
+        /// square outside it, like a bishop shut in on a7 by ...b6 after grabbing a pawn.
+        fn trapped_pieces(board: &Board, is_white: bool, enemy_attacks: u64) -> i32 {
+            let is_safe = |target: u8| {
+                board.piece_color(target) != Some(is_white) && enemy_attacks & (1u64 << target) == 0
+            };
+            let mut penalty = 0;
+            for square in 0..64u8 {
+                if board.piece_color(square) != Some(is_white)
+                    || enemy_attacks & (1u64 << square) == 0
+                {
+                    continue;
+                }
+                let piece = Piece::init_from_binary(board.state[square as usize]);
+                if matches!(piece.class, PieceType::Pawn | PieceType::King) {
+                    continue;
+                }
+                let mut targets = piece.attacked_squares(square, board).into_iter();
+                if !targets.any(is_safe) {
+                    penalty += piece.class.value() / TRAPPED_PIECE_DIVISOR;
+                }
+            }
+            penalty
+        }
+
+        /// Returns the back-rank mate penalty for the king of the given color.
+        ///
+        /// The king counts as trapped when it has castled (it stands on the a-, b-, c-, g- or
+        /// h-file of its back rank) and every square in front of it is taken by its own
+        /// pieces, leaving it no luft. The penalty grows with the number of enemy rooks and queens.
+        fn back_rank_penalty(board: &Board, is_white: bool) -> i32 {
+            let Some(king) = board.king_square(is_white) else {
+                return 0;
+            };
+            let back_rank = if is_white { 7 } else { 0 };
+            let row = position_helper::get_row(king);
+            let col = position_helper::get_col(king);
+            if row != back_rank || (3..=5).contains(&col) {
+                return 0;
+            }
+
+            let front_row = if is_white { row - 1 } else { row + 1 };
+            let first_col = col.saturating_sub(1);
+            let last_col = (col + 1).min(7);
+            let boxed_in = (first_col..=last_col)
+                .all(|front_col| board.piece_color(front_row * 8 + front_col) == Some(is_white));
+            if !boxed_in {
+                return 0;
+            }
+
+            let (rooks, queens) = if is_white {
+                (board.bitboard[7], board.bitboard[10])
+            } else {
+                (board.bitboard[1], board.bitboard[4])
+            };
+            let heavy_pieces = (rooks.count_ones() + queens.count_ones()) as i32;
+            BACK_RANK_PENALTY * heavy_pieces
+        }
+
+        /// Shrinks a score towards a draw as the half move clock nears the fifty-move rule,
+        /// so a winning side prefers moves that reset the clock over ones that run it down.
+        fn scale_for_fifty_move_rule(&self, score: i32) -> i32 {
+            let clock = self.game.half_move_clock();
+            if clock <= FIFTY_MOVE_SCALE_START {
+                return score;
+            }
+            let moves_left = (FIFTY_MOVE_RULE - clock).max(0);
+            score * moves_left / (FIFTY_MOVE_RULE - FIFTY_MOVE_SCALE_START)
+        }
+
+        /// Returns 0 for a king and pawn against king ending the oracle says is drawn, or
+        /// `None` when the ending is won or the material is anything else. Won endings are
+        /// left to `evaluate`, so the search still prefers promoting to staying in the ending.
+        fn kpk_draw_score(&self) -> Option<i32> {
+            let board = &self.game.board;
+            let pawn = PieceType::Pawn.value();
+            if board.material[0] + board.material[1] != pawn {
+                return None;
+            }
+            // Black's pawn is handled by flipping the board so that it becomes white's
+            let strong_is_white = board.material[0] == pawn;
+            let pawns = board.bitboard[if strong_is_white { 0 } else { 6 }];
+            let pawn_square = pawns.trailing_zeros() as u8 ^ 56;
+            let strong_king = board.king_square(strong_is_white)?;
+            let weak_king = board.king_square(!strong_is_white)?;
+            let (wk, bk, wp) = if strong_is_white {
+                (strong_king, weak_king, pawn_square)
+            } else {
+                (strong_king ^ 56, weak_king ^ 56, pawn_square ^ 56)
+            };
+            let strong_to_move = self.game.white_turn == strong_is_white;
+            if kpk::kpk_is_won(strong_to_move, wk, bk, wp) {
+                None
+            } else {
+                Some(0)
+            }
+        }
+
+        /// Searches for the best move and returns it, or a move from a8 to a8 if there is
+        /// no legal move. See `search` for the score, line and statistics of the search.
         pub fn get_best_move(&mut self, depth: u8) -> Move {
-            let start = Instant::now();
-            self.num_positions_evaluated = 0;
-            self.cache_hits_last_eval = 0;
-            let mut best_move = Move {
+            self.search(depth).best_move.unwrap_or(Move {
                 source: 0,
                 target: 0,
                 promotion: 0,
+                flags: 0,
+            })
+        }
+
+        /// Searches the current position `depth` moves deep for each side and reports the
+        /// chosen move along with its score, expected line and search statistics.
+        /// Depths above `MAX_SEARCH_DEPTH` are clamped to it. With the blunder check on, a
+        /// chosen move that hangs material is searched again without it.
+        pub fn search(&mut self, depth: u8) -> SearchResult {
+            let depth = depth.min(MAX_SEARCH_DEPTH);
+            let start = Instant::now();
+            let mut result = self.search_excluding(depth, &[]);
+            if self.blunder_check {
+                result = self.avoid_blunders(depth, result);
+            }
+            result.elapsed = start.elapsed();
+
+            if !self.verbose {
+                return result;
+            }
+            let Some(best_move) = result.best_move else {
+                println!("No legal moves available");
+                return result;
             };
+            let source = position_helper::index_to_letter(best_move.source);
+            let target = position_helper::index_to_letter(best_move.target);
+            println!("Best move: {}{} - score: {}", source, target, result.score);
+
+            println!(
+                "We evaluated {} positions with {} cache hits {}% rate in {:?}",
+                result.nodes,
+                self.cache_hits_last_eval,
+                self.cache_hit_rate() * 100f32,
+                result.elapsed,
+            );
+            result
+        }
+
+        /// Searches like `search`, leaving the root moves in `excluded` out.
+        fn search_excluding(&mut self, depth: u8, excluded: &[Move]) -> SearchResult {
+            let start = Instant::now();
+            self.num_positions_evaluated = 0;
+            self.cache_hits_last_eval = 0;
+            self.root_ply = self.game.history().len();
+            self.pv_table.clear();
+            let mut best_move = None;
+            let mut pv = vec![];
 
             let mut full_depth = depth * 2; // black and white move per depth
             let mut best_score = -100000;
@@ -837,86 +3434,252 @@ pub mod engine {
                 full_depth -= 1;
             }
 
-            let moves = self.game.get_all_moves_for_color(self.game.white_turn);
-            let moves = self.game.remove_illegal_moves(moves);
-            // let moves = self.game.remove_illegal_moves(moves);
+            // Weakened play needs exact scores for every root move, not just the best one
+            let weakened = self.skill_level < MAX_SKILL_LEVEL;
+            let mut root_scores: Vec<(Move, i32)> = vec![];
+
+            let moves = self.game.all_moves_for(self.game.turn());
+            let mut moves = self.game.remove_illegal_moves(moves);
+            moves.retain(|mv| !excluded.contains(mv));
+            self.order_hash_move_first(&mut moves);
             for mv in moves {
                 // make the move
                 let success = self.game.play_move_ob(mv);
                 if !success {
                     continue;
                 }
-                let score = -self.alpha_beta(full_depth, best_score, -best_score);
+                let score = if weakened || best_move.is_none() {
+                    -self.alpha_beta(full_depth, -100000, 100000)
+                } else {
+                    // A null window only proves whether the move beats the best one so far,
+                    // the exact score is searched for when it does
+                    let score = -self.alpha_beta(full_depth, -best_score - 1, -best_score);
+                    if score > best_score {
+                        -self.alpha_beta(full_depth, -100000, -best_score)
+                    } else {
+                        score
+                    }
+                };
 
                 // undo the move
                 self.game.undo_move();
 
-                // update the best move
-                if score > best_score {
+                // update the best move, keeping the first one even if every move loses
+                if score > best_score || best_move.is_none() {
                     best_score = score;
-                    best_move = mv;
+                    best_move = Some(mv);
+                    pv = vec![mv];
+                    pv.extend(self.pv_table.get(1).into_iter().flatten());
                 }
+                root_scores.push((mv, score));
             }
-            let source = position_helper::index_to_letter(best_move.source);
-            let target = position_helper::index_to_letter(best_move.target);
-            if best_move.source == 0 && best_move.target == 0 {
-                println!("No legal moves available");
-                return best_move;
+
+            // The engine's own pick, not the weakened one, is the best move to try next time
+            if let Some(mv) = best_move {
+                self.store_hash_move(mv);
             }
-            println!("Best move: {}{} - score: {}", source, target, best_score,);
 
-            let cash_hit_rate =
-                self.cache_hits_last_eval as f32 / self.num_positions_evaluated as f32;
+            if weakened && !root_scores.is_empty() {
+                let margin = (MAX_SKILL_LEVEL - self.skill_level) as i32 * SKILL_MARGIN_STEP;
+                let candidates: Vec<(Move, i32)> = root_scores
+                    .into_iter()
+                    .filter(|(_, score)| *score >= best_score - margin)
+                    .collect();
+                let (chosen_move, chosen_score) =
+                    candidates[self.rng.gen_range(0..candidates.len())];
+                if best_move != Some(chosen_move) {
+                    // Only the best move's line was kept
+                    best_move = Some(chosen_move);
+                    best_score = chosen_score;
+                    pv = vec![chosen_move];
+                }
+            }
 
-            println!(
-                "We evaluated {} positions with {} cache hits {}% rate in {:?}",
-                self.num_positions_evaluated,
-                self.cache_hits_last_eval,
-                cash_hit_rate * 100f32,
-                start.elapsed(),
-            );
-            best_move
+            SearchResult {
+                best_move,
+                score: best_score,
+                depth,
+                nodes: self.num_positions_evaluated as u64,
+                pv,
+                elapsed: start.elapsed(),
+                hashfull: self.hashfull(),
+            }
         }
 
-        pub fn get_san_from_move(&mut self, chess_move: Move) -> String {
-            let piece_bit = self.game.board.state[chess_move.source as usize];
-            let piece = Piece::init_from_binary(piece_bit);
-            let target = position_helper::index_to_letter(chess_move.target);
-
-            let mut piece_str = "";
-            if piece.is_white {
-                piece_str = match piece.class {
-                    PieceType::King => "K",
-                    PieceType::Queen => "Q",
-                    PieceType::Rook => "R",
-                    PieceType::Bishop => "B",
-                    PieceType::Knight => "N",
-                    PieceType::Pawn => "",
-                };
-            } else {
-                piece_str = match piece.class {
-                    PieceType::King => "k",
-                    PieceType::Queen => "q",
-                    PieceType::Rook => "r",
-                    PieceType::Bishop => "b",
-                    PieceType::Knight => "n",
-                    PieceType::Pawn => "",
-                };
+        /// Searches again without the chosen move for as long as it hangs at least
+        /// `BLUNDER_MARGIN` of material. A move that gives check or leads to a mate keeps its
+        /// compensation, and so does one whose best alternative scores worse than it even
+        /// once the hung material is taken off, as the search then saw something SEE does not.
+        fn avoid_blunders(&mut self, depth: u8, mut result: SearchResult) -> SearchResult {
+            let mut excluded = vec![];
+            while let Some(mv) = result.best_move {
+                let hung = self.material_hung_by(&mv);
+                if hung < BLUNDER_MARGIN
+                    || result.score.abs() > 100000 - MAX_PLY as i32
+                    || self.game.gives_check(&mv)
+                {
+                    break;
+                }
+                excluded.push(mv);
+                let mut alternative = self.search_excluding(depth, &excluded);
+                alternative.nodes += result.nodes;
+                if alternative.best_move.is_none() || alternative.score < result.score - hung {
+                    result.nodes = alternative.nodes;
+                    break;
+                }
+                result = alternative;
+            }
+            result
+        }
+
+        /// Returns the material, in centipawns, that the opponent wins by `see` with its best
+        /// capture after `mv`, less what `mv` itself captures. Returns 0 if `mv` cannot be
+        /// played or leaves nothing to win.
+        pub fn material_hung_by(&self, mv: &Move) -> i32 {
+            let captured = self
+                .game
+                .capture_target(mv)
+                .map_or(0, |(piece, _)| piece.value());
+            let mut after = self.game.clone();
+            if !after.play_move_ob(*mv) {
+                return 0;
+            }
+            let lost = after
+                .get_capture_moves()
+                .iter()
+                .filter(|capture| after.is_legal(capture))
+                .map(|capture| after.see(capture))
+                .max()
+                .unwrap_or(0);
+            (lost - captured).max(0)
+        }
+
+        /// Searches every legal move of the side to move with a full window, clamped like
+        /// `search`, and returns each move with its exact score for the side to move, in
+        /// generation order. Slower than `search`, which only proves the other moves are worse
+        /// than the best one, but gives the scores needed to rank or compare them.
+        pub fn root_move_scores(&mut self, depth: u8) -> Vec<(Move, i32)> {
+            let depth = depth.min(MAX_SEARCH_DEPTH);
+            let mut full_depth = depth * 2; // black and white move per depth
+            if self.game.white_turn {
+                full_depth -= 1;
+            }
+            self.num_positions_evaluated = 0;
+            self.root_ply = self.game.history().len();
+
+            let mut scores = vec![];
+            for mv in self.game.legal_moves_for(self.game.turn()) {
+                if !self.game.play_move_ob(mv) {
+                    continue;
+                }
+                let score = -self.alpha_beta(full_depth, -100000, 100000);
+                self.game.undo_move();
+                scores.push((mv, score));
+            }
+            scores
+        }
+
+        /// Searches the position given as a FEN, like `search`, without touching the engine's
+        /// own game, which is swapped for a scratch game during the search and restored after.
+        pub fn best_move_for_fen(
+            &mut self,
+            fen: &str,
+            depth: u8,
+        ) -> Result<SearchResult, FenError> {
+            let mut scratch = Game::init();
+            scratch.try_set_from_fen(fen)?;
+            let live_game = std::mem::replace(&mut self.game, scratch);
+            let result = self.search(depth);
+            self.game = live_game;
+            Ok(result)
+        }
+
+        /// Classifies `mv` by how many centipawns it loses against the best move, with both
+        /// moves searched to the same depth, clamped like `search`, and a full window. The
+        /// game is left as it was. An illegal move counts as a blunder.
+        pub fn classify_move(&mut self, mv: &Move, depth: u8) -> MoveQuality {
+            let scores = self.root_move_scores(depth);
+            let Some(&(_, played_score)) = scores.iter().find(|(candidate, _)| candidate == mv)
+            else {
+                return MoveQuality::Blunder;
+            };
+            let best_score = scores
+                .iter()
+                .map(|&(_, score)| score)
+                .max()
+                .unwrap_or(-100000);
+
+            match best_score - played_score {
+                loss if loss <= 0 => MoveQuality::Best,
+                loss if loss <= GOOD_MOVE_LOSS => MoveQuality::Good,
+                loss if loss <= INACCURACY_LOSS => MoveQuality::Inaccuracy,
+                loss if loss <= MISTAKE_LOSS => MoveQuality::Mistake,
+                _ => MoveQuality::Blunder,
             }
-            return format!("{}{}", piece_str, target);
+        }
 
+        /// Returns the SAN of `chess_move` in the engine's current position.
+        pub fn get_san_from_move(&mut self, chess_move: Move) -> String {
+            self.game.move_to_san(chess_move)
         }
 
         pub fn alpha_beta(&mut self, depth: u8, mut alpha: i32, beta: i32) -> i32 {
             // Update the counter
             self.num_positions_evaluated += 1;
 
+            // The line below this node is rebuilt from scratch
+            let ply = self.game.history().len().saturating_sub(self.root_ply);
+            if self.pv_table.len() <= ply + 1 {
+                self.pv_table.resize(ply + 2, vec![]);
+            }
+            self.pv_table[ply].clear();
+
+            // A side without a king (only reachable from a malformed position) has already lost
+            if self.game.board.king_square(self.game.white_turn).is_none() {
+                return -100000;
+            }
+
+            // Dead draws have nothing left to search and must not be scored as a loss
+            if self.game.is_insufficient_material() {
+                return 0;
+            }
+            // Past the fifty-move rule the side to move may take a draw instead of playing on,
+            // so the node scores at least 0, unless it has just been checkmated
+            let can_claim_draw = self.game.can_claim_fifty_move_draw();
+            if can_claim_draw {
+                if beta <= 0 || self.game.half_move_clock() >= SEVENTY_FIVE_MOVE_RULE {
+                    return 0;
+                }
+                alpha = alpha.max(0);
+            }
+            if let Some(score) = self.kpk_draw_score() {
+                return score;
+            }
+
             if depth == 0 {
-                return self.evaluate(&self.game.board.clone());
+                return self.quiescence(alpha, beta);
             }
-            let mut best_score = -100000;
-            let moves = self.game.get_all_moves_for_color(self.game.white_turn);
-            let moves = self.game.remove_illegal_moves(moves);
+
+            // Mates further from the root score lower, so the winning side takes the shortest
+            let mut best_score = -100000 + ply as i32;
+            let mut best_move = None;
+            let futility_score = self.futility_score(depth, alpha);
+            let moves = match futility_score {
+                // Only checks, promotions and captures that win back enough by SEE can lift
+                // a hopeless score to alpha
+                Some(score) => {
+                    let game = &self.game;
+                    let mut moves = game.get_capture_moves();
+                    moves.retain(|mv| score + game.see(mv) > alpha || game.gives_check(mv));
+                    let quiets = game.get_quiet_moves().into_iter();
+                    moves.extend(quiets.filter(|mv| mv.is_promotion() || game.gives_check(mv)));
+                    moves
+                }
+                None => self.game.all_moves_for(self.game.turn()),
+            };
+            let mut moves = self.game.remove_illegal_moves(moves);
+            // The best move from an earlier visit is the most likely to cause a cutoff
+            self.order_hash_move_first(&mut moves);
             for mv in moves {
                 let success = self.game.play_move_ob(mv);
                 if !success {
@@ -926,15 +3689,168 @@ pub mod engine {
                 self.game.undo_move();
                 if score > best_score {
                     best_score = score;
+                    best_move = Some(mv);
                 }
                 if score > alpha {
                     alpha = score;
+                    let mut line = vec![mv];
+                    line.extend_from_slice(&self.pv_table[ply + 1]);
+                    self.pv_table[ply] = line;
+                }
+                if alpha >= beta {
+                    break;
+                }
+            }
+            // Claiming the draw stays open to the side to move whatever the moves score
+            let with_claim = |score: i32| if can_claim_draw { score.max(0) } else { score };
+            let Some(mv) = best_move else {
+                // Every move may have been pruned, which only says that none reaches alpha
+                if let Some(score) = futility_score {
+                    if !self.game.legal_moves_for(self.game.turn()).is_empty() {
+                        return with_claim(score);
+                    }
+                }
+                // Without a legal move it is mate when in check and a draw otherwise, so a
+                // winning side steers clear of stalemating the opponent
+                if !self.game.is_in_check() {
+                    return 0;
+                }
+                return best_score;
+            };
+            self.store_hash_move(mv);
+            // Pruned moves fail low as well, but may score up to the futility score
+            with_claim(match futility_score {
+                Some(score) => best_score.max(score),
+                None => best_score,
+            })
+        }
+
+        /// Returns the static score of the side to move plus the futility margin of `depth`
+        /// when it cannot reach `alpha`, so that quiet moves are not worth searching. Returns
+        /// `None` when pruning is off, the depth is above 2, the side to move is in check or
+        /// alpha is a mate score.
+        fn futility_score(&mut self, depth: u8, alpha: i32) -> Option<i32> {
+            let margin = *FUTILITY_MARGINS.get(depth as usize)?;
+            if !self.futility_pruning || alpha.abs() > 100000 - MAX_PLY as i32 {
+                return None;
+            }
+            let score = self.stand_pat_score() + margin;
+            (score <= alpha && !self.game.is_in_check()).then_some(score)
+        }
+
+        /// Returns the static evaluation of the current position for the side to move,
+        /// shrunk towards a draw as the fifty-move rule nears.
+        fn stand_pat_score(&mut self) -> i32 {
+            let score = self.evaluate(&self.game.board.clone());
+            let score = self.scale_for_fifty_move_rule(score);
+            if self.game.white_turn {
+                score
+            } else {
+                -score
+            }
+        }
+
+        /// Keeps searching captures after the main search ends, until the position is quiet,
+        /// so that it is never scored in the middle of an exchange. Scores are relative to the
+        /// side to move. A side in check may not stand pat on the static evaluation, so all of
+        /// its legal evasions are searched instead, and having none scores as a loss.
+        pub fn quiescence(&mut self, mut alpha: i32, beta: i32) -> i32 {
+            self.num_positions_evaluated += 1;
+
+            if self.game.board.king_square(self.game.white_turn).is_none() {
+                return -100000;
+            }
+            if self.game.is_insufficient_material() {
+                return 0;
+            }
+            let can_claim_draw = self.game.can_claim_fifty_move_draw();
+            if can_claim_draw {
+                if beta <= 0 || self.game.half_move_clock() >= SEVENTY_FIVE_MOVE_RULE {
+                    return 0;
+                }
+                alpha = alpha.max(0);
+            }
+            if let Some(score) = self.kpk_draw_score() {
+                return score;
+            }
+
+            let in_check = self.game.is_in_check();
+            let ply = self.game.history().len().saturating_sub(self.root_ply);
+            let mut best_score = -100000 + ply as i32;
+            let moves = if in_check {
+                self.game.legal_moves_for(self.game.turn())
+            } else {
+                let stand_pat = self.stand_pat_score();
+                if stand_pat >= beta {
+                    return stand_pat;
                 }
+                best_score = stand_pat;
+                alpha = alpha.max(stand_pat);
+                // Captures are searched pseudolegally: one that leaves the king in check is
+                // refuted by the king being taken next, which is cheaper than filtering them.
+                // Try the most valuable victims first, taken by the least valuable attackers.
+                let mut captures = self.game.get_capture_moves();
+                let piece_value = |square: u8| {
+                    Piece::init_from_binary(self.game.board.state[square as usize])
+                        .class
+                        .value()
+                };
+                captures.sort_by_cached_key(|mv| (-piece_value(mv.target), piece_value(mv.source)));
+                captures
+            };
+
+            for mv in moves {
+                if !self.game.play_move_ob(mv) {
+                    continue;
+                }
+                let score = -self.quiescence(-beta, -alpha);
+                self.game.undo_move();
+                best_score = best_score.max(score);
+                alpha = alpha.max(score);
                 if alpha >= beta {
                     break;
                 }
             }
-            best_score
+            if can_claim_draw {
+                best_score.max(0)
+            } else {
+                best_score
+            }
+        }
+    }
+
+    /// Plays a game between two engines from the position of `white`'s game, each searching
+    /// `depth` moves deep, and returns how it ended. The game is adjudicated a draw, with
+    /// `GameStatus::MoveLimit`, once each side has played `max_moves` moves without it ending
+    /// by the rules. Both engines' games hold the finished game afterwards.
+    ///
+    /// Engines built with a seed play the same game every time, which makes matches between
+    /// two versions of the engine reproducible.
+    pub fn play_engine_game(
+        white: &mut Engine,
+        black: &mut Engine,
+        max_moves: u32,
+        depth: u8,
+    ) -> GameStatus {
+        black.game = white.game.clone();
+        let mut plies = 0;
+        loop {
+            let status = white.game.status();
+            if status != GameStatus::Ongoing {
+                return status;
+            }
+            if plies >= max_moves.saturating_mul(2) {
+                return GameStatus::MoveLimit;
+            }
+            let engine = if white.game.white_turn {
+                &mut *white
+            } else {
+                &mut *black
+            };
+            let mv = engine.get_best_move(depth);
+            white.game.play_move_ob(mv);
+            black.game.play_move_ob(mv);
+            plies += 1;
         }
     }
 }
@@ -1018,14 +3934,73 @@ pub mod psqt {
         -50,-30,-30,-30,-30,-30,-30,-50
     ];
 
+    /// Pawn table that rewards a solid centre over racing pawns up the board.
+    pub const PAWN_POSITIONAL: [i32; 64] = [
+        100, 100, 100, 100, 100, 100, 100, 100,
+        130, 130, 130, 130, 130, 130, 130, 130,
+        110, 110, 115, 120, 120, 115, 110, 110,
+        105, 105, 110, 125, 125, 110, 105, 105,
+        100, 100, 105, 120, 120, 105, 100, 100,
+        100,  95,  95, 100, 100,  95,  95, 100,
+        100, 105, 105,  80,  80, 105, 105, 100,
+        100, 100, 100, 100, 100, 100, 100, 100
+    ];
+
+    /// Knight table that values development off the back rank and punishes the rim harder.
+    pub const KNIGHT_POSITIONAL: [i32; 64] = [
+        250, 260, 270, 270, 270, 270, 260, 250,
+        260, 280, 300, 300, 300, 300, 280, 260,
+        270, 300, 310, 315, 315, 310, 300, 270,
+        270, 305, 315, 320, 320, 315, 305, 270,
+        270, 300, 315, 320, 320, 315, 300, 270,
+        270, 305, 310, 315, 315, 310, 305, 270,
+        260, 280, 300, 305, 305, 300, 280, 260,
+        250, 260, 270, 270, 270, 270, 260, 250
+    ];
+
+    /// A full set of piece-square tables, one per piece type plus the endgame king table.
+    pub struct Tables {
+        pub pawn: &'static [i32; 64],
+        pub knight: &'static [i32; 64],
+        pub bishop: &'static [i32; 64],
+        pub rook: &'static [i32; 64],
+        pub queen: &'static [i32; 64],
+        pub king: &'static [i32; 64],
+        pub king_late: &'static [i32; 64],
+    }
+
+    /// The default tables, which push pawns and pieces forward.
+    pub const AGGRESSIVE: Tables = Tables {
+        pawn: &PAWN,
+        knight: &KNIGHT,
+        bishop: &BISHOP,
+        rook: &ROOK,
+        queen: &QUEEN,
+        king: &KING,
+        king_late: &KING_LATE,
+    };
+
+    /// Tables for a quieter style that develops pieces before advancing pawns.
+    pub const POSITIONAL: Tables = Tables {
+        pawn: &PAWN_POSITIONAL,
+        knight: &KNIGHT_POSITIONAL,
+        bishop: &BISHOP,
+        rook: &ROOK,
+        queen: &QUEEN,
+        king: &KING,
+        king_late: &KING_LATE,
+    };
+
+    /// Maps a square to the same square seen from Black's side (the rank is mirrored, the
+    /// file is kept), so Black pieces can use the tables above, which are written for White.
     pub const FLIP: [usize; 64] = [
-        63, 62, 61, 60, 59, 58, 57, 56,
-        55, 54, 53, 52, 51, 50, 49, 48,
-        47, 46, 45, 44, 43, 42, 41, 40,
-        39, 38, 37, 36, 35, 34, 33, 32,
-        31, 30, 29, 28, 27, 26, 25, 24,
-        23, 22, 21, 20, 19, 18, 17, 16,
-        15, 14, 13, 12, 11, 10,  9,  8,
-        7,  6,  5,  4,  3,  2,  1,  0
+        56, 57, 58, 59, 60, 61, 62, 63,
+        48, 49, 50, 51, 52, 53, 54, 55,
+        40, 41, 42, 43, 44, 45, 46, 47,
+        32, 33, 34, 35, 36, 37, 38, 39,
+        24, 25, 26, 27, 28, 29, 30, 31,
+        16, 17, 18, 19, 20, 21, 22, 23,
+         8,  9, 10, 11, 12, 13, 14, 15,
+         0,  1,  2,  3,  4,  5,  6,  7
     ];
 }
@@ -1,48 +1,38 @@
-use cherris::{self, engine::Engine, position_helper, ChessDebugInfo, ChessGame, Move};
+use cherris::{
+    self,
+    engine::{Engine, EngineBuilder, Personality},
+    position_helper, ChessDebugInfo, ChessGame, Move,
+};
 use color_eyre::eyre::Result;
 use lazy_static::lazy_static;
-use rand::Rng;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use cherris::constants::{ PIECE_BIT, WHITE_BIT, QUEEN };
-
 lazy_static! {
-    static ref ENGINE: Arc<Mutex<Engine>> = Arc::new(Mutex::new(Engine::init()));
+    // The desktop app logs its searches to the terminal it was started from
+    static ref ENGINE: Arc<Mutex<Engine>> =
+        Arc::new(Mutex::new(EngineBuilder::new().verbose(true).build()));
 }
 
 #[cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 #[tauri::command]
-fn set_from_fen(fen: &str) -> String {
+fn set_from_fen(fen: &str) -> Result<String, String> {
     let game = &mut ENGINE.lock().unwrap().game;
-    game.set_from_fen(fen.to_string());
-    game.get_fen()
+    game.try_set_from_fen(fen)
+        .map_err(|error| error.to_string())?;
+    Ok(game.get_fen())
 }
 
 
-fn play_move(source: &str, target: &str, promotion: &str) -> String {
+#[tauri::command]
+fn play_move(source: &str, target: &str, promotion: &str) -> Result<String, String> {
     println!("We want to move from {} to {}.", source, target);
     let game = &mut ENGINE.lock().unwrap().game;
 
-
-    // Force promotion to a queen for now 
-    let promotion_piece = match promotion {
-        "Q" => PIECE_BIT + WHITE_BIT + QUEEN,
-        "q" => PIECE_BIT + QUEEN,
-            _ => 0,
-        };
-
-    let move_obj = Move {
-        source: position_helper::letter_to_index(source.to_string()),
-        target: position_helper::letter_to_index(target.to_string()),
-        promotion: promotion_piece,
-    };
-
-    let is_legal = game.play_move_ob(move_obj);
-
-    println!("The move legality was {}", is_legal);
-    // get the FEN String
-    let fen = game.get_fen_simple();
-    fen
+    // The full FEN, like the other commands, so the frontend keeps the clocks and rights
+    let result = game.try_play_move(source, target, promotion);
+    println!("The move legality was {}", result.is_ok());
+    result.map_err(|error| error.to_string())
 }
 
 #[tauri::command]
@@ -54,16 +44,37 @@ fn restart_game() {
 
 // TODO: We shoud return the FEN here
 #[tauri::command]
-fn undo_move() {
+fn undo_move() -> bool {
     let game = &mut ENGINE.lock().unwrap().game;
-    game.undo_move();
+    game.undo_move()
+}
+
+#[tauri::command]
+fn undo_moves(count: usize) -> String {
+    let game = &mut ENGINE.lock().unwrap().game;
+    game.undo_moves(count);
+    game.get_fen()
 }
 
 #[tauri::command]
-fn get_possible_moves(source: &str) -> Vec<String> {
+fn get_history() -> Vec<String> {
+    let game = &ENGINE.lock().unwrap().game;
+    game.history().to_vec()
+}
+
+#[tauri::command]
+fn goto_ply(ply: usize) -> String {
+    let game = &mut ENGINE.lock().unwrap().game;
+    game.goto(ply);
+    game.get_fen()
+}
+
+#[tauri::command]
+fn get_possible_moves(source: &str) -> Result<Vec<String>, String> {
+    position_helper::try_letter_to_index(source).map_err(|error| error.to_string())?;
     let game = &mut ENGINE.lock().unwrap().game;
     game.undo_move();
-    game.get_pseudolegal_moves(source.to_string())
+    Ok(game.get_pseudolegal_moves(source.to_string()))
 }
 
 #[tauri::command]
@@ -74,41 +85,98 @@ fn get_fen() -> String {
 
 
 #[tauri::command]
-fn get_piece_at_square(square: &str) -> String {
-    let game = &mut ENGINE.lock().unwrap().game;
-    game.get_piece_at_square(square.to_string())
+fn get_game_status() -> String {
+    let game = &ENGINE.lock().unwrap().game;
+    game.status().as_str().to_string()
 }
 
+#[tauri::command]
+fn get_check_info() -> Option<String> {
+    let game = &ENGINE.lock().unwrap().game;
+    game.checked_king_square().map(position_helper::index_to_letter)
+}
+
+#[tauri::command]
+fn get_attacked_squares(square: &str) -> Result<Vec<String>, String> {
+    let game = &ENGINE.lock().unwrap().game;
+    let square = position_helper::try_letter_to_index(square).map_err(|error| error.to_string())?;
+    Ok(game
+        .attacks_from(square)
+        .into_iter()
+        .map(position_helper::index_to_letter)
+        .collect())
+}
 
 #[tauri::command]
-fn make_random_move() -> String {
+fn get_piece_at_square(square: &str) -> Result<String, String> {
+    position_helper::try_letter_to_index(square).map_err(|error| error.to_string())?;
     let game = &mut ENGINE.lock().unwrap().game;
-    let moves = game.get_legal_moves(game.white_turn);
+    Ok(game.get_piece_at_square(square.to_string()))
+}
 
-    if moves.is_empty() {
-        println!("No legal moves available");
-        return "None".to_string();
-    }
 
-    // select a random move
+/// Result of `make_random_move`, so the frontend can tell a stalemate from a checkmate
+/// when no move could be played.
+#[derive(serde::Serialize)]
+struct RandomMoveResult {
+    played: bool,
+    fen: String,
+    status: String,
+}
+
+#[tauri::command]
+fn make_random_move(avoid_mate: Option<bool>, safe: Option<bool>) -> RandomMoveResult {
+    let game = &mut ENGINE.lock().unwrap().game;
     let mut rng = rand::thread_rng();
-    let random_index = rng.gen_range(0..moves.len());
-    let random_move = moves[random_index];
+    let avoid_mate = avoid_mate.unwrap_or(false);
+    // Pure random unless asked not to hang pieces
+    let random_move = if safe.unwrap_or(false) {
+        game.safe_random_move(&mut rng, avoid_mate)
+    } else {
+        game.random_move(&mut rng, avoid_mate)
+    };
 
-    game.play_move_ob(random_move);
+    let played = match random_move {
+        Some(mv) => game.play_move_ob(mv),
+        None => {
+            println!("No legal moves available");
+            false
+        }
+    };
     let fen = game.get_fen();
     println!("The FEN was: {}", fen);
-    return fen;
+    RandomMoveResult {
+        played,
+        fen,
+        status: game.status().as_str().to_string(),
+    }
+}
+
+#[tauri::command]
+fn set_engine_difficulty(level: u8) {
+    let mut engine = ENGINE.lock().unwrap();
+    engine.set_difficulty(level);
 }
 
 #[tauri::command]
-fn get_engine_move(depth: i32) -> String {
-    println!("Playing best move with depth: {}", depth);
+fn set_engine_personality(name: &str) -> bool {
+    let Some(personality) = Personality::from_name(name) else {
+        return false;
+    };
+    ENGINE.lock().unwrap().set_personality(personality);
+    true
+}
+
+#[tauri::command]
+fn get_engine_move() -> String {
     let mut engine = ENGINE.lock().unwrap();
-    let best_move = engine.get_best_move(depth as u8);
-    let source_square = position_helper::index_to_letter(best_move.source);
-    let target_square = position_helper::index_to_letter(best_move.target);
-    println!("The best move was {} to {}", source_square, target_square);
+    println!("Playing best move with depth: {}", engine.search_depth());
+    let depth = engine.search_depth();
+    let result = engine.search(depth);
+    let Some(best_move) = result.best_move else {
+        return engine.game.get_fen();
+    };
+    println!("{}", result.info_line());
     engine.game.play_move_ob(best_move);
     engine.game.get_fen()
 }
@@ -116,7 +184,7 @@ fn get_engine_move(depth: i32) -> String {
 #[tauri::command]
 fn get_legal_moves(source: &str) -> Vec<String> {
     let game = &mut ENGINE.lock().unwrap().game;
-    let moves = game.get_legal_moves(game.white_turn);
+    let moves = game.legal_moves_for(game.turn());
     let mut result = Vec::new();
     for m in moves {
         if position_helper::index_to_letter(m.source) == source {
@@ -126,6 +194,27 @@ fn get_legal_moves(source: &str) -> Vec<String> {
     result
 }
 
+#[tauri::command]
+fn is_move_legal(source: &str, target: &str, promotion: &str) -> bool {
+    let game = &ENGINE.lock().unwrap().game;
+    let uci = format!("{}{}{}", source, target, promotion.to_lowercase());
+    Move::from_uci(&uci).is_some_and(|mv| game.is_legal(&mv))
+}
+
+#[tauri::command]
+fn get_position_hash() -> String {
+    // Sent as hex, a u64 does not fit in a JavaScript number
+    let game = &ENGINE.lock().unwrap().game;
+    format!("{:016x}", game.hash())
+}
+
+#[tauri::command]
+fn get_legal_moves_map() -> HashMap<String, Vec<String>> {
+    let game = &ENGINE.lock().unwrap().game;
+    game.legal_moves_map()
+}
+
+/// Sets the board from a piece placement, or from a full FEN if one is given.
 #[tauri::command]
 fn set_fen(fen: &str) -> bool {
     let game = &mut ENGINE.lock().unwrap().game;
@@ -138,14 +227,26 @@ fn main() -> Result<()> {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             set_from_fen,
+            play_move,
             restart_game,
             undo_move,
+            undo_moves,
+            get_history,
+            goto_ply,
             get_fen,
+            get_game_status,
+            get_check_info,
             get_piece_at_square,
+            get_attacked_squares,
             get_possible_moves,
             make_random_move,
             get_engine_move,
+            set_engine_difficulty,
+            set_engine_personality,
             get_legal_moves,
+            get_legal_moves_map,
+            is_move_legal,
+            get_position_hash,
             set_fen,
         ])
         .run(tauri::generate_context!())
@@ -1,13 +1,60 @@
 use crate::board::Board;
 use crate::constants::BISHOP;
+use crate::constants::CAPTURE_FLAG;
+use crate::constants::CASTLE_FLAG;
+use crate::constants::DOUBLE_PUSH_FLAG;
+use crate::constants::EN_PASSANT_FLAG;
+use crate::constants::KING;
 use crate::constants::KNIGHT;
+use crate::constants::PAWN_BIT;
 use crate::constants::PIECE_BIT;
+use crate::constants::PROMOTION_FLAG;
 use crate::constants::QUEEN;
 use crate::constants::ROOK;
 use crate::position_helper;
 use crate::Move;
 use crate::{CHECK_PIECE, COL, ROW, WHITE_BIT};
 
+/// The `(row, col)` steps a rook slides along.
+const ROOK_DIRECTIONS: [(i16, i16); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// The `(row, col)` steps a bishop slides along.
+const BISHOP_DIRECTIONS: [(i16, i16); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// The `(row, col)` steps a king moves by, which a queen also slides along.
+const KING_DIRECTIONS: [(i16, i16); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// The `(row, col)` jumps a knight moves by.
+const KNIGHT_JUMPS: [(i16, i16); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+/// Returns the capture flag for a move onto `target` if the square holds a piece.
+/// Callers only generate moves onto empty squares or enemy pieces.
+fn capture_flag(target: u8, board: &Board) -> u8 {
+    if board.is_empty(target) {
+        0
+    } else {
+        CAPTURE_FLAG
+    }
+}
+
 /// Represents a chess piece.
 #[derive(Debug, Clone)]
 pub struct Piece {
@@ -27,7 +74,102 @@ pub enum PieceType {
     King,
 }
 
+impl PieceType {
+    /// Returns the material value of the piece type in centipawns.
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 300,
+            PieceType::Bishop => 320,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 10000,
+        }
+    }
+
+    /// Returns how much the piece type counts towards the game phase.
+    /// Knights and bishops count 1, rooks 2 and queens 4, so the starting position adds up to 24.
+    pub fn phase_weight(&self) -> i32 {
+        match self {
+            PieceType::Knight | PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 4,
+            PieceType::Pawn | PieceType::King => 0,
+        }
+    }
+
+    /// Returns the FEN character of the piece type, uppercase for white pieces.
+    pub fn to_char(&self, white: bool) -> char {
+        let c = match self {
+            PieceType::Pawn => 'p',
+            PieceType::Rook => 'r',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        if white {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+
+    /// Parses a FEN character of either case into a piece type.
+    pub fn from_char(c: char) -> Option<PieceType> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(PieceType::Pawn),
+            'r' => Some(PieceType::Rook),
+            'n' => Some(PieceType::Knight),
+            'b' => Some(PieceType::Bishop),
+            'q' => Some(PieceType::Queen),
+            'k' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+
+    /// Returns the binary representation of a piece of this type, as stored on the board.
+    pub fn to_binary(&self, white: bool) -> u8 {
+        let piece_bits = match self {
+            PieceType::Pawn => PAWN_BIT,
+            PieceType::Rook => ROOK,
+            PieceType::Knight => KNIGHT,
+            PieceType::Bishop => BISHOP,
+            PieceType::Queen => QUEEN,
+            PieceType::King => KING,
+        };
+        if white {
+            PIECE_BIT + WHITE_BIT + piece_bits
+        } else {
+            PIECE_BIT + piece_bits
+        }
+    }
+}
+
 impl Piece {
+    /// Decodes a piece byte, returning `None` instead of guessing when the byte is not the
+    /// encoding of a piece: the piece bit is missing or the type bits are not one of the
+    /// six piece types. An empty square (0) is not a piece either.
+    pub fn try_from_binary(binary: u8) -> Option<Piece> {
+        if binary & PIECE_BIT == 0 || binary & !(PIECE_BIT | WHITE_BIT | CHECK_PIECE) != 0 {
+            return None;
+        }
+        let class = match binary & CHECK_PIECE {
+            PAWN_BIT => PieceType::Pawn,
+            KING => PieceType::King,
+            QUEEN => PieceType::Queen,
+            BISHOP => PieceType::Bishop,
+            KNIGHT => PieceType::Knight,
+            ROOK => PieceType::Rook,
+            _ => return None,
+        };
+        Some(Piece {
+            binary,
+            is_white: binary & WHITE_BIT == WHITE_BIT,
+            class,
+        })
+    }
+
     /// Calculates the possible moves for a pawn.
     ///
     /// # Arguments
@@ -46,34 +188,28 @@ impl Piece {
         let one_row = (source as i16) + multiplier * (ROW as i16);
         let two_rows = (source as i16) + multiplier * (ROW as i16) * 2;
 
+        // Rows are counted from rank 8, so the home row is row 6 (rank 2) for white pawns
+        // and row 1 (rank 7) for black pawns
         let move_double_forward = if self.is_white { 6 } else { 1 };
 
-        if board
-            .state
-            .get((one_row) as usize)
-            .is_some_and(|x| *x == 0u8)
-        {
+        if board.is_empty(one_row as u8) {
             possible_moves.push(Move {
                 source,
                 target: one_row as u8,
                 promotion: 0,
+                flags: 0,
             });
         }
 
         if move_double_forward == position_helper::get_row(source)
-            && board
-                .state
-                .get((two_rows) as usize)
-                .is_some_and(|x| *x == 0u8)
-            && board
-                .state
-                .get((one_row) as usize)
-                .is_some_and(|x| *x == 0u8)
+            && board.is_empty(two_rows as u8)
+            && board.is_empty(one_row as u8)
         {
             possible_moves.push(Move {
                 source,
                 target: two_rows as u8,
                 promotion: 0,
+                flags: DOUBLE_PUSH_FLAG,
             });
         }
 
@@ -84,101 +220,65 @@ impl Piece {
         //check the position to avoid taking on the other side
         let col = position_helper::get_col(source);
 
-        if col < 7
-            && (board
-                .state
-                .get(diagonal_right as usize)
-                .is_some_and(|x| *x != 0u8)
-                || board.en_passant == diagonal_right as u8)
-        {
-            possible_moves.push(Move {
-                source,
-                target: diagonal_right as u8,
-                promotion: 0,
-            });
-        }
+        // An en passant square of 0 means there is none, rather than a8. The square only
+        // counts with an enemy pawn beside this one to take, so a stale square loaded from a
+        // FEN cannot make up a capture of nothing
+        let is_en_passant = |target: i16| {
+            let captured = (target - multiplier * (ROW as i16)) as u8;
+            board.en_passant != 0
+                && board.en_passant == target as u8
+                && board.is_empty(target as u8)
+                && board.piece_on(captured) == Some((PieceType::Pawn, !self.is_white))
+        };
 
-        if col > 0
-            && (board
-                .state
-                .get(diagonal_left as usize)
-                .is_some_and(|x| *x != 0u8)
-                || board.en_passant == diagonal_left as u8)
-        {
+        let capture_flags = |target: i16| {
+            if board.is_enemy(target as u8, self.is_white) {
+                Some(CAPTURE_FLAG)
+            } else if is_en_passant(target) {
+                Some(CAPTURE_FLAG | EN_PASSANT_FLAG)
+            } else {
+                None
+            }
+        };
+
+        for (target, on_board) in [(diagonal_right, col < 7), (diagonal_left, col > 0)] {
+            let Some(flags) = capture_flags(target).filter(|_| on_board) else {
+                continue;
+            };
             possible_moves.push(Move {
                 source,
-                target: diagonal_left as u8,
+                target: target as u8,
                 promotion: 0,
+                flags,
             });
         }
 
-        //TODO: Remove the "unpromoted" pawn move
-        // Generate the white promotions
-        if col == 6 && self.is_white {
-            for i in 0..possible_moves.len(){
-                let mv = possible_moves[i];
-                if mv.target == 8u8 {
-                    // append promotions
-                    let mut queen_mv = mv;
-                    queen_mv.promotion = PIECE_BIT + WHITE_BIT + QUEEN;
-
-                    let mut bishop_mv = mv;
-                    bishop_mv.promotion = PIECE_BIT + WHITE_BIT + BISHOP;
-
-                    let mut rook_mv = mv;
-                    rook_mv.promotion = PIECE_BIT + WHITE_BIT + ROOK;
-
-                    let mut knight_mv = mv;
-                    knight_mv.promotion = PIECE_BIT + WHITE_BIT + KNIGHT;
-
-                    possible_moves.push(queen_mv);
-                    possible_moves.push(bishop_mv);
-                    possible_moves.push(rook_mv);
-                    possible_moves.push(knight_mv);
-
-                }
-            }
-        }
-
-        // Generate black promotions
-        if col == 2 && !self.is_white {
-            for i in 0..possible_moves.len() {
-                let mv = possible_moves[i];
-                if mv.target == 8u8 {
-                    // append promotions
-                    let mut queen_mv = mv;
-                    queen_mv.promotion = PIECE_BIT + QUEEN;
-
-                    let mut bishop_mv = mv;
-                    bishop_mv.promotion = PIECE_BIT + BISHOP;
-
-                    let mut rook_mv = mv;
-                    rook_mv.promotion = PIECE_BIT + ROOK;
-
-                    let mut knight_mv = mv;
-                    knight_mv.promotion = PIECE_BIT + KNIGHT;
-
-                    possible_moves.push(queen_mv);
-                    possible_moves.push(bishop_mv);
-                    possible_moves.push(rook_mv);
-                    possible_moves.push(knight_mv);
-
-                }
-            }
-        }
-
-        for i in (0..possible_moves.len()).rev() {
-            if self.is_white && possible_moves[i].target == 7 && possible_moves[i].promotion == 0 {
-                possible_moves.remove(i);
+        // A pawn reaching the last row must promote, so each such move becomes one move per
+        // promotion piece, in the pawn's own color
+        let promotion_row = if self.is_white { 0 } else { 7 };
+        let promotion_pieces = [
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+        ];
+        let mut possible_moves_with_promotions = Vec::new();
+        for mv in possible_moves {
+            if position_helper::get_row(mv.target) != promotion_row {
+                possible_moves_with_promotions.push(mv);
+                continue;
             }
-            if !self.is_white && possible_moves[i].target == 0 && possible_moves[i].promotion == 0 {
-                possible_moves.remove(i);
+            for piece_type in promotion_pieces.iter() {
+                possible_moves_with_promotions.push(Move {
+                    promotion: piece_type.to_binary(self.is_white),
+                    flags: mv.flags | PROMOTION_FLAG,
+                    ..mv
+                });
             }
         }
 
-
         let mut final_positions = Vec::new();
-        for mv in possible_moves {
+        for mv in possible_moves_with_promotions {
             if position_helper::is_position_valid(mv.target, board, self.is_white) {
                 final_positions.push(mv);
             }
@@ -213,6 +313,7 @@ impl Piece {
                     source,
                     target: new_position as u8,
                     promotion: 0,
+                    flags: capture_flag(new_position as u8, board),
                 });
             }
         }
@@ -270,6 +371,7 @@ impl Piece {
                     source,
                     target: source + 2,
                     promotion: 0,
+                    flags: CASTLE_FLAG,
                 });
             }
         }
@@ -290,6 +392,7 @@ impl Piece {
                     source,
                     target: source - 2,
                     promotion: 0,
+                    flags: CASTLE_FLAG,
                 });
             }
         }
@@ -308,78 +411,8 @@ impl Piece {
     ///
     /// A vector containing the possible positions the rook can move to.
     fn rook_moves(&self, source: u8, board: &Board) -> Vec<Move> {
-        let mut possible_positions = Vec::<Move>::new();
-        let row = position_helper::get_row(source);
-        let col = position_helper::get_col(source);
-
-        let mut blocked_right: bool = false;
-        let mut blocked_up: bool = false;
-        let mut blocked_down: bool = false;
-        let mut blocked_left: bool = false;
         // move up, down, left, and right from the current position
-        // check that there is no piece in the way
-        for i in 1..8 {
-            if col + i < 8 && !blocked_right {
-                // check right boundary
-                let position_to_check = source + i;
-                let piece_retrieved = board.state.get(position_to_check as usize);
-
-                // If a piece is found, we are now blocked from moving forward
-                blocked_right = piece_retrieved.is_some_and(|x| *x != 0u8);
-                possible_positions.push(Move {
-                    source,
-                    target: source + i,
-                    promotion: 0,
-                });
-            }
-            if i <= col && !blocked_left {
-                // check left boundary
-                let position_to_check = source - i;
-                let piece_retrieved = board.state.get(position_to_check as usize);
-
-                // If a piece is found, we are now blocked from moving forward
-                blocked_left = piece_retrieved.is_some_and(|x| *x != 0u8);
-                possible_positions.push(Move {
-                    source,
-                    target: source - i,
-                    promotion: 0,
-                });
-            }
-            if row + i < 8 && !blocked_down {
-                // check lower boundary
-                let position_to_check = source + ROW * i;
-                let piece_retrieved = board.state.get(position_to_check as usize);
-
-                // If a piece is found, we are now blocked from moving forward
-                blocked_down = piece_retrieved.is_some_and(|x| *x != 0u8);
-                possible_positions.push(Move {
-                    source,
-                    target: source + ROW * i,
-                    promotion: 0,
-                });
-            }
-            if i <= row && !blocked_up {
-                // check upper boundary
-                let position_to_check = source - ROW * i;
-                let piece_retrieved = board.state.get(position_to_check as usize);
-
-                blocked_up = piece_retrieved.is_some_and(|x| *x != 0u8);
-                possible_positions.push(Move {
-                    source,
-                    target: source - ROW * i,
-                    promotion: 0,
-                });
-            }
-        }
-
-        let mut final_positions = Vec::new();
-        for mv in possible_positions {
-            if position_helper::is_position_valid(mv.target, board, self.is_white) {
-                final_positions.push(mv);
-            }
-        }
-
-        final_positions
+        self.sliding_moves(source, board, &ROOK_DIRECTIONS)
     }
 
     /// Calculates the possible moves for a queen.
@@ -411,71 +444,98 @@ impl Piece {
     ///
     /// A vector containing the possible positions the bishop can move to.
     fn bishop_moves(&self, source: u8, board: &Board) -> Vec<Move> {
-        let row = position_helper::get_row(source);
-        let col = position_helper::get_col(source);
-        let mut blocked_up_left = false;
-        let mut blocked_down_left = false;
-        let mut blocked_up_right = false;
-        let mut blocked_down_right = false;
-
-        (1..8)
-            .flat_map(|i| {
-                let mut moves = Vec::new();
-
-                if col + i < 8 {
-                    if row + i < 8 && !blocked_down_right {
-                        let position_to_check = source + i + ROW * i;
-                        let piece_retrieved = board.state.get(position_to_check as usize);
-
-                        blocked_down_right = piece_retrieved.is_some_and(|x| *x != 0u8);
-                        moves.push(Move {
-                            source,
-                            target: source + i + ROW * i,
-                            promotion: 0,
-                        });
-                    }
-                    if i <= row && !blocked_up_right {
-                        let position_to_check = source + i - ROW * i;
-                        let piece_retrieved = board.state.get(position_to_check as usize);
-
-                        blocked_up_right = piece_retrieved.is_some_and(|x| *x != 0u8);
-                        moves.push(Move {
-                            source,
-                            target: source + i - ROW * i,
-                            promotion: 0,
-                        });
-                    }
+        self.sliding_moves(source, board, &BISHOP_DIRECTIONS)
+    }
+
+    /// Calculates the possible moves for a sliding piece along the given directions.
+    ///
+    /// Rows and columns are computed as `i16` and bound-checked before being cast back
+    /// to a `u8` square, so walking off an edge of the board can never wrap around.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The current position of the piece.
+    /// * `board` - The chess board.
+    /// * `directions` - The `(row, col)` steps the piece slides along.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing the possible positions the piece can move to.
+    fn sliding_moves(&self, source: u8, board: &Board, directions: &[(i16, i16)]) -> Vec<Move> {
+        let mut possible_positions = Vec::<Move>::new();
+        let row = position_helper::get_row(source) as i16;
+        let col = position_helper::get_col(source) as i16;
+
+        for (row_step, col_step) in directions {
+            for i in 1..8 {
+                let new_row = row + row_step * i;
+                let new_col = col + col_step * i;
+                if !(0..8).contains(&new_row) || !(0..8).contains(&new_col) {
+                    break;
                 }
 
-                if i <= col {
-                    if row + i < 8 && !blocked_down_left {
-                        let position_to_check = source - i + ROW * i;
-                        let piece_retrieved = board.state.get(position_to_check as usize);
-
-                        blocked_down_left = piece_retrieved.is_some_and(|x| *x != 0u8);
-                        moves.push(Move {
-                            source,
-                            target: source - i + ROW * i,
-                            promotion: 0,
-                        });
-                    }
-                    if i <= row && !blocked_up_left {
-                        let position_to_check = source - i - ROW * i;
-                        let piece_retrieved = board.state.get(position_to_check as usize);
-
-                        blocked_up_left = piece_retrieved.is_some_and(|x| *x != 0u8);
-                        moves.push(Move {
-                            source,
-                            target: (source - i - ROW * i),
-                            promotion: 0,
-                        });
-                    }
+                let target = (new_row * ROW as i16 + new_col * COL as i16) as u8;
+                if board.is_empty(target) || board.is_enemy(target, self.is_white) {
+                    possible_positions.push(Move {
+                        source,
+                        target,
+                        promotion: 0,
+                        flags: capture_flag(target, board),
+                    });
                 }
 
-                moves
-            })
-            .filter(|&mv| position_helper::is_position_valid(mv.target, board, self.is_white))
-            .collect()
+                // If a piece is found, we are now blocked from moving forward
+                if !board.is_empty(target) {
+                    break;
+                }
+            }
+        }
+
+        possible_positions
+    }
+
+    /// Returns every square the piece attacks from `source`, whether or not it could move there.
+    ///
+    /// Unlike `possible_moves`, this includes squares held by friendly pieces, which the piece
+    /// defends, and leaves out pawn pushes and castling, which never capture.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The current position of the piece.
+    /// * `board` - The chess board.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing the attacked squares.
+    pub fn attacked_squares(&self, source: u8, board: &Board) -> Vec<u8> {
+        let forward: i16 = if self.is_white { -1 } else { 1 };
+        let pawn_captures = [(forward, -1), (forward, 1)];
+        let (steps, sliding): (&[(i16, i16)], bool) = match self.class {
+            PieceType::Pawn => (&pawn_captures, false),
+            PieceType::Knight => (&KNIGHT_JUMPS, false),
+            PieceType::King => (&KING_DIRECTIONS, false),
+            PieceType::Rook => (&ROOK_DIRECTIONS, true),
+            PieceType::Bishop => (&BISHOP_DIRECTIONS, true),
+            PieceType::Queen => (&KING_DIRECTIONS, true),
+        };
+
+        let mut attacked = Vec::new();
+        let row = position_helper::get_row(source) as i16;
+        let col = position_helper::get_col(source) as i16;
+        for (row_step, col_step) in steps {
+            let mut new_row = row + row_step;
+            let mut new_col = col + col_step;
+            while (0..8).contains(&new_row) && (0..8).contains(&new_col) {
+                let target = (new_row * ROW as i16 + new_col * COL as i16) as u8;
+                attacked.push(target);
+                if !sliding || !board.is_empty(target) {
+                    break;
+                }
+                new_row += row_step;
+                new_col += col_step;
+            }
+        }
+        attacked
     }
 
     /// Calculates the possible moves for a knight.
@@ -507,6 +567,7 @@ impl Piece {
                     source: position,
                     target: new_position as u8,
                     promotion: 0,
+                    flags: capture_flag(new_position as u8, board),
                 });
             }
         }
@@ -582,24 +643,8 @@ impl BasicPiece for Piece {
     ///
     /// A `String` representing the text representation of the piece.
     fn text_repr(&self) -> String {
-        let mut return_string = String::from("");
-        let color_string: String = if self.is_white {
-            String::from("w")
-        } else {
-            String::from("b")
-        };
-
-        let piece_string = match self.class {
-            PieceType::Pawn => "P".to_string(),
-            PieceType::King => "K".to_string(),
-            PieceType::Queen => "Q".to_string(),
-            PieceType::Bishop => "B".to_string(),
-            PieceType::Knight => "N".to_string(),
-            PieceType::Rook => "R".to_string(),
-        };
-        return_string.push_str(&color_string);
-        return_string.push_str(&piece_string);
-        return_string
+        let color_string = if self.is_white { "w" } else { "b" };
+        format!("{}{}", color_string, self.class.to_char(true))
     }
 
     /// Returns the FEN representation of the piece.
@@ -608,19 +653,7 @@ impl BasicPiece for Piece {
     ///
     /// A `String` representing the FEN representation of the piece.
     fn fen_repr(&self) -> String {
-        let mut piece_string = match self.class {
-            PieceType::Pawn => "P",
-            PieceType::King => "K",
-            PieceType::Queen => "Q",
-            PieceType::Bishop => "B",
-            PieceType::Knight => "N",
-            PieceType::Rook => "R",
-        }
-        .to_string();
-        if !self.is_white {
-            piece_string = piece_string.to_lowercase();
-        }
-        piece_string
+        self.class.to_char(self.is_white).to_string()
     }
 }
 
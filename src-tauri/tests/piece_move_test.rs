@@ -9,20 +9,29 @@ const QUEEN: u8 = 1u8;
 const BISHOP: u8 = 2u8;
 const KNIGHT: u8 = 4u8;
 const ROOK: u8 = 6u8;
+const CAPTURE_FLAG: u8 = 1u8;
 // const ROW: u8 = 16u8;
 // const COL: u8 = 1u8;
 
 use crate::position_helper;
 use cherris::board::Board;
-use cherris::engine::Engine;
+use cherris::engine::{self, Engine, EngineBuilder, MoveQuality, MAX_SEARCH_DEPTH};
+use cherris::kpk;
 use cherris::piece::{BasicPiece, Piece, PieceType};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// Returns a game set up from `fen`.
+fn game_from_fen(fen: &str) -> Game {
+    let mut game = Game::init();
+    game.try_set_from_fen(fen).unwrap();
+    game
+}
 
 #[test]
 fn test_engine() {
     let mut engine = Engine::init();
     let fen = "rnbqkbnr/pp3ppp/2p1P3/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 4 1".to_string();
-    engine.game.set_from_fen(fen.clone());
+    engine.game.try_set_from_fen(&fen).unwrap();
     let best_move = engine.get_best_move(1);
     let allowed_move = engine.game.play_move_ob(best_move);
     assert!(allowed_move);
@@ -37,7 +46,7 @@ fn test_index_to_letters() {
 #[test]
 fn test_letters_to_index() {
     let cell = String::from("d8");
-    let index = position_helper::letter_to_index(cell);
+    let index = position_helper::try_letter_to_index(&cell).unwrap();
     assert_eq!(index, 3);
 }
 
@@ -45,7 +54,7 @@ fn test_letters_to_index() {
 fn test_pawn_initial_move_emtpy_board() {
     let board = Board::init();
     let pos_string: String = String::from("a2");
-    let position = position_helper::letter_to_index(pos_string);
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let white_pawn = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + PAWN_BIT);
     let possible_positions: Vec<String> = white_pawn
         .possible_moves(position, &board)
@@ -59,8 +68,8 @@ fn test_pawn_initial_move_emtpy_board() {
 fn test_pawn_cannot_take_in_front() {
     let fen_string = "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen_string);
-    let allowed_move = game.play_move_from_string("e4", "e5", "");
+    game.try_set_from_fen(&fen_string).unwrap();
+    let allowed_move = game.try_play_move("e4", "e5", "").is_ok();
     assert!(!allowed_move);
 }
 
@@ -68,7 +77,7 @@ fn test_pawn_cannot_take_in_front() {
 fn test_king_moves_empty_board() {
     let board = Board::init();
     let pos_string: String = String::from("a1");
-    let position = position_helper::letter_to_index(pos_string);
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let king = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + KING);
     let mut possible_positions: Vec<String> = king
         .possible_moves(position, &board)
@@ -87,7 +96,7 @@ fn test_king_moves_empty_board() {
 fn test_rook_moves_empty_board() {
     let board = Board::init();
     let pos_string: String = String::from("d4");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let rook = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + ROOK);
     let possible_positions: HashSet<String> = rook
         .possible_moves(position, &board)
@@ -112,7 +121,7 @@ fn test_rook_moves_starting_board() {
     let mut board = Board::init();
     board.set_start_position();
     let pos_string: String = String::from("a1");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let rook = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + ROOK);
     let possible_positions: HashSet<String> = rook
         .possible_moves(position, &board)
@@ -130,7 +139,7 @@ fn test_rook_moves_starting_board() {
 fn test_bishop_moves_empty_board() {
     let board = Board::init();
     let pos_string: String = String::from("d4");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let bishop = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + BISHOP);
     let possible_positions: HashSet<String> = bishop
         .possible_moves(position, &board)
@@ -155,7 +164,7 @@ fn test_bishop_moves_starting_board() {
     let mut board = Board::init();
     board.set_start_position();
     let pos_string: String = String::from("c1");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let bishop = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + BISHOP);
     let possible_positions: HashSet<String> = bishop
         .possible_moves(position, &board)
@@ -175,7 +184,7 @@ fn test_queen_moves_starting_board() {
     let mut board = Board::init();
     board.set_start_position();
     let pos_string: String = String::from("c1");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let queen = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + QUEEN);
     let possible_positions: HashSet<String> = queen
         .possible_moves(position, &board)
@@ -194,7 +203,7 @@ fn test_queen_moves_starting_board() {
 fn test_queen_moves_empty_board() {
     let board = Board::init();
     let pos_string: String = String::from("d4");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let queen = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + QUEEN);
     let possible_positions: HashSet<String> = queen
         .possible_moves(position, &board)
@@ -219,7 +228,7 @@ fn test_queen_moves_empty_board() {
 fn test_knight_moves_empty_board() {
     let board = Board::init();
     let pos_string: String = String::from("d4");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let knight = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + KNIGHT);
     let possible_positions: HashSet<String> = knight
         .possible_moves(position, &board)
@@ -241,7 +250,7 @@ fn test_knight_moves_empty_board() {
 fn test_knight_move_edge_board() {
     let board = Board::init();
     let pos_string: String = String::from("a1");
-    let position = position_helper::letter_to_index(pos_string.clone());
+    let position = position_helper::try_letter_to_index(&pos_string).unwrap();
     let knight = Piece::init_from_binary(PIECE_BIT + WHITE_BIT + KNIGHT);
     let possible_positions: HashSet<String> = knight
         .possible_moves(position, &board)
@@ -259,12 +268,102 @@ fn test_knight_move_edge_board() {
     assert_eq!(possible_positions, correct_position);
 }
 
+/// Targets of a lone piece on an empty board, worked out with bitboard shifts independently
+/// of the move generator. Bits count from a1, like `Board::bitboard`.
+fn lone_piece_targets(class: &PieceType, is_white: bool, from: u64) -> u64 {
+    const FILE_A: u64 = 0x0101_0101_0101_0101;
+    const FILE_H: u64 = 0x8080_8080_8080_8080;
+    const RANK_2: u64 = 0x0000_0000_0000_FF00;
+    const RANK_7: u64 = 0x00FF_0000_0000_0000;
+    let north = |bits: u64| bits << 8;
+    let south = |bits: u64| bits >> 8;
+    let east = |bits: u64| (bits << 1) & !FILE_A;
+    let west = |bits: u64| (bits >> 1) & !FILE_H;
+    let slide = |step: &dyn Fn(u64) -> u64| {
+        let mut targets = 0;
+        let mut bits = step(from);
+        while bits != 0 {
+            targets |= bits;
+            bits = step(bits);
+        }
+        targets
+    };
+    let straight = || slide(&north) | slide(&south) | slide(&east) | slide(&west);
+    let diagonal = || {
+        slide(&|bits| north(east(bits)))
+            | slide(&|bits| north(west(bits)))
+            | slide(&|bits| south(east(bits)))
+            | slide(&|bits| south(west(bits)))
+    };
+
+    match class {
+        // Only a pawn on its starting rank can push twice
+        PieceType::Pawn if is_white => north(from) | north(north(from & RANK_2)),
+        PieceType::Pawn => south(from) | south(south(from & RANK_7)),
+        PieceType::Knight => {
+            let sideways = [east(from), west(from)];
+            let wide = [east(east(from)), west(west(from))];
+            sideways
+                .iter()
+                .map(|&bits| north(north(bits)) | south(south(bits)))
+                .chain(wide.iter().map(|&bits| north(bits) | south(bits)))
+                .fold(0, |targets, bits| targets | bits)
+        }
+        PieceType::King => {
+            let row = from | east(from) | west(from);
+            (row | north(row) | south(row)) & !from
+        }
+        PieceType::Rook => straight(),
+        PieceType::Bishop => diagonal(),
+        PieceType::Queen => straight() | diagonal(),
+    }
+}
+
+#[test]
+fn test_lone_piece_moves_match_bitboards() {
+    let classes = [
+        PieceType::Pawn,
+        PieceType::Rook,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+    for class in classes {
+        for is_white in [true, false] {
+            // Pawns never stand on their last rank, so theirs is the corner of the squares
+            // they can reach: a2 for white and a7 for black, where they may push twice
+            let corner = match (&class, is_white) {
+                (PieceType::Pawn, true) => "a2",
+                (PieceType::Pawn, false) => "a7",
+                _ => "a1",
+            };
+            for square in ["d4", corner] {
+                let position = position_helper::try_letter_to_index(square).unwrap();
+                let mut board = Board::empty();
+                board.place(position, class.clone(), is_white);
+                let piece = Piece::init_from_binary(board.state[position as usize]);
+                let generated = piece
+                    .possible_moves(position, &board)
+                    .iter()
+                    .fold(0u64, |targets, mv| targets | 1u64 << (mv.target ^ 56));
+                let expected = lone_piece_targets(&class, is_white, 1u64 << (position ^ 56));
+                assert_eq!(
+                    generated, expected,
+                    "{:?} (white: {}) on {}: generated {:#018x}, expected {:#018x}",
+                    class, is_white, square, generated, expected
+                );
+            }
+        }
+    }
+}
+
 #[test]
 fn test_validate_position_in_board() {
     let mut board = Board::init();
     board.set_start_position();
     let final_string: String = String::from("a1");
-    let final_position = position_helper::letter_to_index(final_string.clone());
+    let final_position = position_helper::try_letter_to_index(&final_string).unwrap();
     let valid_position = position_helper::is_position_valid(final_position, &board, true);
     assert!(!valid_position);
 }
@@ -282,7 +381,7 @@ fn test_update_from_fen() {
     let fen_after_e4_move =
         "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen_after_e4_move.clone());
+    game.try_set_from_fen(&fen_after_e4_move).unwrap();
     let fen2 = game.get_fen();
     assert_eq!(fen2, fen_after_e4_move);
 }
@@ -291,7 +390,7 @@ fn test_update_from_fen() {
 fn test_update_from_fen2() {
     let fen = "rnbqkbnr/pp3ppp/2p5/3pN3/4P3/2P5/PP1P1PPP/RNBQKB1R b KQkq - 0 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen.clone());
+    game.try_set_from_fen(&fen).unwrap();
     let fen2 = game.get_fen();
     assert_eq!(fen2, fen);
 }
@@ -320,8 +419,8 @@ fn test_take_with_black_pawn() {
      */
     let fen = "rnbqkbnr/pp3ppp/2p5/3pN3/4P3/2P5/PP1P1PPP/RNBQKB1R b KQkq - 0 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen.clone());
-    let allowed_move = game.play_move_from_string("d5","e4", "");
+    game.try_set_from_fen(&fen).unwrap();
+    let allowed_move = game.try_play_move("d5", "e4", "").is_ok();
     assert!(allowed_move);
 }
 
@@ -329,8 +428,8 @@ fn test_take_with_black_pawn() {
 fn test_undo_move() {
     let fen = "rnbqkbnr/pp3ppp/2p5/3pN3/4P3/2P5/PP1P1PPP/RNBQKB1R b KQkq - 0 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen.clone());
-    let allowed_move = game.play_move_from_string("d5", "e4", "");
+    game.try_set_from_fen(&fen).unwrap();
+    let allowed_move = game.try_play_move("d5", "e4", "").is_ok();
     assert!(allowed_move);
     game.undo_move();
     let fen2 = game.get_fen();
@@ -341,20 +440,20 @@ fn test_undo_move() {
 fn test_queen_in_position() {
     let fen = "rnbqkbnr/ppp2ppp/4P3/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 4 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen.clone());
+    game.try_set_from_fen(&fen).unwrap();
     game.board.show();
-    let allowed_move = game.play_move_from_string("d8", "d6", "");
+    let allowed_move = game.try_play_move("d8", "d6", "").is_ok();
     assert!(allowed_move);
 }
 
 #[test]
 fn test_en_passant_take() {
     let mut game = Game::init();
-    game.play_move_from_string("e2", "e4", "");
-    game.play_move_from_string("a7", "a6", "");
-    game.play_move_from_string("e4","e5", "");
-    game.play_move_from_string("d7","d5","");
-    let valid_move = game.play_move_from_string("e5","d6","");
+    game.try_play_move("e2", "e4", "").unwrap();
+    game.try_play_move("a7", "a6", "").unwrap();
+    game.try_play_move("e4", "e5", "").unwrap();
+    game.try_play_move("d7", "d5", "").unwrap();
+    let valid_move = game.try_play_move("e5", "d6", "").is_ok();
     assert!(valid_move);
 }
 
@@ -362,7 +461,7 @@ fn test_en_passant_take() {
 fn test_fen_serde() {
     let fen = "rnbqkbnr/ppp2ppp/4P3/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 4 1".to_string();
     let mut game = Game::init();
-    game.set_from_fen(fen.clone());
+    game.try_set_from_fen(&fen).unwrap();
     let fen2 = game.get_fen();
     assert_eq!(fen2, fen);
 }
@@ -397,7 +496,7 @@ fn test_position_start() {
 #[test]
 fn test_en_passant_flag() {
     let mut game = Game::init();
-    game.play_move_from_string("e2", "e4", "");
+    game.try_play_move("e2", "e4", "").unwrap();
     assert_eq!(game.en_passant, "e3");
 }
 
@@ -432,8 +531,8 @@ fn test_queen_moves_from_fen() {
       |----|----|----|----|----|----|----|----|
         a    b    c    d    e    f    g    h
     */
-    game.set_from_fen(fen.clone());
-    let initial_position = position_helper::letter_to_index("d1".to_string());
+    game.try_set_from_fen(&fen).unwrap();
+    let initial_position = position_helper::try_letter_to_index("d1").unwrap();
     let white_queen_bits = game.board.state.get(initial_position as usize).unwrap();
     let queen = Piece::init_from_binary(*white_queen_bits);
     let possible_positions: HashSet<String> = queen
@@ -477,8 +576,9 @@ fn update_castling_after_taken_rook() {
      */
 
     let mut game = Game::init();
-    game.set_from_fen("rnbqkb1r/pppppppp/8/8/3N4/2PP2n1/PP2PPPP/RNBQKB1R b KQkq - 0 4".to_string());
-    let allowed_move = game.play_move_from_string("g3", "h1", "");
+    game.try_set_from_fen("rnbqkb1r/pppppppp/8/8/3N4/2PP2n1/PP2PPPP/RNBQKB1R b KQkq - 0 4")
+        .unwrap();
+    let allowed_move = game.try_play_move("g3", "h1", "").is_ok();
     assert!(allowed_move);
 
     // Check that the castling rights have been updated - the white king should not be able to castle on the kingside
@@ -509,7 +609,8 @@ fn test_legal_moves_should_allow_taking_piece_to_avoid_check() {
 
      */
     let mut game = Game::init();
-    game.set_from_fen("rnb1kbnr/ppp1pppp/8/3P4/8/4q3/PP3PPP/RNBQKBNR w KQkq - 0 7".to_string());
+    game.try_set_from_fen("rnb1kbnr/ppp1pppp/8/3P4/8/4q3/PP3PPP/RNBQKBNR w KQkq - 0 7")
+        .unwrap();
     let moves = game.get_legal_moves(game.white_turn);
     for mv in moves.clone() {
       let source = position_helper::index_to_letter(mv.source);
@@ -523,22 +624,22 @@ fn test_legal_moves_should_allow_taking_piece_to_avoid_check() {
 fn test_legal_move_generation() {
     let mut new_game = Game::init();
     let start = Instant::now();
-    let moves = perft(1, &mut new_game);
+    let moves = new_game.perft(1);
     let elapsed = start.elapsed();
     println!("Time taken for depth 1: {:?}", elapsed);
     
     let start = Instant::now();
-    let moves2 = perft(2, &mut new_game);
+    let moves2 = new_game.perft(2);
     let elapsed = start.elapsed();
     println!("Time taken for depth 2: {:?}", elapsed);
     
     let start = Instant::now();
-    let moves3 = perft(3, &mut new_game);
+    let moves3 = new_game.perft(3);
     let elapsed = start.elapsed();
     println!("Time taken for depth 3: {:?}", elapsed);
     
     let start = Instant::now();
-    let moves4 = perft(4, &mut new_game);
+    let moves4 = new_game.perft(4);
     let elapsed = start.elapsed();
     println!("Time taken for depth 4: {:?}", elapsed);
     assert_eq!(moves, 20);
@@ -550,24 +651,25 @@ fn test_legal_move_generation() {
 #[test]
 fn test_legal_move_generation_postion1() {
     let mut game = Game::init();
-    game.set_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 0".to_string());
+    game.try_set_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 0")
+        .unwrap();
     let start = Instant::now();
-    let moves = perft(1, &mut game);
+    let moves = game.perft(1);
     let elapsed = start.elapsed();
     println!("Time taken for depth 1: {:?}", elapsed);
 
     let start = Instant::now();
-    let moves2 = perft(2, &mut game);
+    let moves2 = game.perft(2);
     let elapsed = start.elapsed();
     println!("Time taken for depth 2: {:?}", elapsed);
 
     let start = Instant::now();
-    let moves3 = perft(3, &mut game);
+    let moves3 = game.perft(3);
     let elapsed = start.elapsed();
     println!("Time taken for depth 3: {:?}", elapsed);
 
     // let start = Instant::now();
-    // let moves4 = perft(4, &mut game);
+    // let moves4 = game.perft(4);
     // let elapsed = start.elapsed();
     // println!("Time taken for depth 4: {:?}", elapsed);
 
@@ -578,25 +680,6 @@ fn test_legal_move_generation_postion1() {
 
 } 
 
-fn perft(depth: u8, game: &mut Game) -> usize {
-    if depth == 0 {
-        return 1;
-    }
-    let mut count = 0;
-
-    let moves = game.get_legal_moves(game.white_turn);
-    if depth == 1 {
-      return moves.len();
-    }
-
-    for mv in moves {
-        game.play_move_ob(mv);
-        count += perft(depth - 1, game);
-        game.undo_move();
-    }
-    count
-}
-
 #[test]
 fn test_promotion() {
   let mut game = Game::init();
@@ -605,6 +688,7 @@ fn test_promotion() {
     source: 8u8,
     target: 0,
     promotion: PIECE_BIT + WHITE_BIT + QUEEN,
+    flags: 0,
   };
   let success = game.play_move(mv, false);
   
@@ -613,3 +697,2888 @@ fn test_promotion() {
   let pawn_should_be_queen = Piece::init_from_binary(game.board.state[0]);
   assert_eq!(pawn_should_be_queen.class, PieceType::Queen);
 }
+
+fn move_from_squares(source: &str, target: &str) -> Move {
+    Move {
+        source: position_helper::try_letter_to_index(source).unwrap(),
+        target: position_helper::try_letter_to_index(target).unwrap(),
+        promotion: 0,
+        flags: 0,
+    }
+}
+
+#[test]
+fn test_pv_to_san() {
+    let game = Game::init();
+    let pv = vec![
+        move_from_squares("e2", "e4"),
+        move_from_squares("e7", "e5"),
+        move_from_squares("g1", "f3"),
+        move_from_squares("b8", "c6"),
+    ];
+    assert_eq!(game.pv_to_san(&pv), vec!["e4", "e5", "Nf3", "Nc6"]);
+
+    // Fool's mate: the final move must carry the mate suffix
+    let pv = vec![
+        move_from_squares("f2", "f3"),
+        move_from_squares("e7", "e5"),
+        move_from_squares("g2", "g4"),
+        move_from_squares("d8", "h4"),
+    ];
+    assert_eq!(game.pv_to_san(&pv), vec!["f3", "e5", "g4", "Qh4#"]);
+}
+
+#[test]
+fn test_board_square_helpers() {
+    let mut board = Board::init();
+    board.set_start_position();
+
+    // Corners hold pieces of both colors
+    assert!(!board.is_empty(0));
+    assert_eq!(board.piece_color(0), Some(false));
+    assert_eq!(board.piece_color(63), Some(true));
+    assert!(board.is_enemy(0, true));
+    assert!(!board.is_enemy(0, false));
+    assert!(board.is_enemy(63, false));
+
+    // Empty squares in the middle of the board
+    let e4 = position_helper::try_letter_to_index("e4").unwrap();
+    assert!(board.is_empty(e4));
+    assert_eq!(board.piece_color(e4), None);
+    assert!(!board.is_enemy(e4, true));
+    assert!(!board.is_enemy(e4, false));
+
+    // Squares outside the board are neither empty nor occupied
+    assert!(!board.is_empty(64));
+    assert_eq!(board.piece_color(64), None);
+    assert!(!board.is_enemy(64, true));
+}
+
+#[test]
+fn test_sliding_pieces_in_corners() {
+    let board = Board::init();
+    let corners = ["a1", "a8", "h1", "h8"];
+    let pieces = [(ROOK, 14), (BISHOP, 7), (QUEEN, 21)];
+    for corner in corners {
+        let position = position_helper::try_letter_to_index(corner).unwrap();
+        for (piece_bits, expected_moves) in pieces {
+            for color in [0, WHITE_BIT] {
+                let piece = Piece::init_from_binary(PIECE_BIT + color + piece_bits);
+                let moves = piece.possible_moves(position, &board);
+                assert_eq!(moves.len(), expected_moves, "{:?} on {}", piece.class, corner);
+                assert!(moves.iter().all(|mv| mv.target < 64));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_castling_flags_reflect_moves() {
+    // Moving the king removes both castling options for that side only
+    let mut game = Game::init();
+    assert!(game.try_play_move("e2", "e4", "").is_ok());
+    assert!(game.try_play_move("e7", "e5", "").is_ok());
+    assert!(game.try_play_move("e1", "e2", "").is_ok());
+    assert_eq!(game.board.castling, 0b0000_0011);
+    assert_eq!(game.board.get_castling_fen(), "kq");
+
+    // Moving a rook removes the option on its side
+    assert!(game.try_play_move("h7", "h6", "").is_ok());
+    assert!(game.try_play_move("a2", "a3", "").is_ok());
+    assert!(game.try_play_move("h8", "h7", "").is_ok());
+    assert_eq!(game.board.get_castling_fen(), "q");
+
+    // Capturing a rook only affects the castling options of the side that lost it
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k2r/8/8/8/8/8/1B6/R3K2R w KQkq - 0 1")
+        .unwrap();
+    assert!(game.try_play_move("b2", "h8", "").is_ok());
+    assert_eq!(game.board.get_castling_fen(), "KQq");
+}
+
+#[test]
+fn test_engine_difficulty_controls_search_depth() {
+    let fen = "4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1".to_string();
+
+    let mut easy_engine = Engine::init();
+    easy_engine.game.try_set_from_fen(&fen).unwrap();
+    easy_engine.set_difficulty(1);
+    let easy_move = easy_engine.get_best_move_default();
+
+    let mut hard_engine = Engine::init();
+    hard_engine.game.try_set_from_fen(&fen).unwrap();
+    hard_engine.set_difficulty(5);
+    let hard_move = hard_engine.get_best_move_default();
+
+    assert!(easy_engine.search_depth() < hard_engine.search_depth());
+    assert!(easy_engine.nodes_searched() < hard_engine.nodes_searched());
+    assert!(easy_engine.game.clone().play_move_ob(easy_move));
+    assert!(hard_engine.game.clone().play_move_ob(hard_move));
+
+    // Out of range levels are clamped
+    hard_engine.set_difficulty(200);
+    assert_eq!(hard_engine.search_depth(), 3);
+    hard_engine.set_difficulty(0);
+    assert_eq!(hard_engine.search_depth(), 1);
+}
+
+#[test]
+fn test_engine_skill_level() {
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2".to_string();
+
+    let mut reference_engine = Engine::init();
+    reference_engine.game.try_set_from_fen(&fen).unwrap();
+    let best_move = reference_engine.get_best_move(1);
+
+    // Full strength always plays the best move regardless of the seed
+    for seed in 0..3 {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(&fen).unwrap();
+        engine.set_seed(seed);
+        assert_eq!(engine.skill_level(), cherris::engine::MAX_SKILL_LEVEL);
+        assert_eq!(engine.get_best_move(1), best_move);
+    }
+
+    // The weakest level plays legal moves, but not always the best one
+    let mut found_other_move = false;
+    for seed in 0..10 {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(&fen).unwrap();
+        engine.set_skill_level(0);
+        engine.set_seed(seed);
+        let mv = engine.get_best_move(1);
+        assert!(engine.game.get_legal_moves(false).contains(&mv));
+        found_other_move |= mv != best_move;
+    }
+    assert!(found_other_move);
+
+    // The same seed reproduces the same choice
+    let mut first = Engine::init();
+    first.game.try_set_from_fen(&fen).unwrap();
+    first.set_skill_level(0);
+    first.set_seed(42);
+    let mut second = Engine::init();
+    second.game.try_set_from_fen(&fen).unwrap();
+    second.set_skill_level(0);
+    second.set_seed(42);
+    assert_eq!(first.get_best_move(1), second.get_best_move(1));
+}
+
+#[test]
+fn test_capture_to_bare_kings_is_a_draw() {
+    let mut game = Game::init();
+    game.try_set_from_fen("8/8/1k6/8/3r4/3K4/8/8 w - - 0 1")
+        .unwrap();
+    assert!(!game.is_insufficient_material());
+    assert_eq!(game.status(), GameStatus::Ongoing);
+
+    assert!(game.try_play_move("d3", "d4", "").is_ok());
+    assert!(game.is_insufficient_material());
+    assert_eq!(game.status(), GameStatus::InsufficientMaterial);
+    assert!(game.status().is_draw());
+
+    // The game is over, so the kings cannot keep shuffling
+    assert!(game.try_play_move("b6", "b5", "").is_err());
+
+    // King and a minor piece against a king cannot mate either
+    game.try_set_from_fen("8/8/8/3k4/8/3K4/8/6N1 w - - 0 1")
+        .unwrap();
+    assert!(game.is_insufficient_material());
+    game.try_set_from_fen("8/8/8/3k4/8/3K4/8/5BB1 w - - 0 1")
+        .unwrap();
+    assert!(!game.is_insufficient_material());
+}
+
+#[test]
+fn test_undo_without_history() {
+    let mut game = Game::init();
+    let fen = game.get_fen();
+    assert!(!game.undo_move());
+    assert_eq!(game.get_fen(), fen);
+
+    assert!(game.try_play_move("e2", "e4", "").is_ok());
+    assert!(game.undo_move());
+    assert_eq!(game.get_fen(), fen);
+    assert!(!game.undo_move());
+}
+
+#[test]
+fn test_mirror_position() {
+    let mut game = Game::init();
+    game.try_set_from_fen("rnbqkbnr/pppp1ppp/8/8/3pP3/5N2/PPP2PPP/RNBQKB1R b Kq e3 0 3")
+        .unwrap();
+    let mirrored = game.mirror();
+    assert_eq!(
+        mirrored.get_fen(),
+        "rnbqkb1r/ppp2ppp/5n2/3Pp3/8/8/PPPP1PPP/RNBQKBNR w Qk e6 0 3"
+    );
+    assert_eq!(mirrored.mirror().get_fen(), game.get_fen());
+
+    // The start position is its own mirror with the other side to move
+    let start = Game::init().mirror();
+    assert_eq!(
+        start.get_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"
+    );
+    assert_eq!(start.board.bitboard, Game::init().board.bitboard);
+}
+
+#[test]
+fn test_evaluation_is_color_symmetric() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2k5/8/4P3/8/8/5K2/8 w - - 0 1",
+        "4r1k1/1p3ppp/p7/8/8/P4N2/1P3PPP/3R2K1 b - - 0 1",
+    ];
+    for fen in fens {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        let mirrored = engine.game.mirror();
+        let score = engine.evaluate(&engine.game.board.clone());
+        let mirrored_score = engine.evaluate(&mirrored.board);
+        assert_eq!(score, -mirrored_score, "{}", fen);
+    }
+}
+
+#[test]
+fn test_piece_type_char_round_trip() {
+    let piece_types = [
+        PieceType::Pawn,
+        PieceType::Rook,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+    for piece_type in piece_types {
+        for white in [true, false] {
+            let c = piece_type.to_char(white);
+            assert_eq!(c.is_uppercase(), white);
+            assert_eq!(PieceType::from_char(c), Some(piece_type.clone()));
+
+            let piece = Piece::init_from_binary(piece_type.to_binary(white));
+            assert_eq!(piece.class, piece_type);
+            assert_eq!(piece.is_white, white);
+            assert_eq!(piece.fen_repr(), c.to_string());
+        }
+    }
+    assert_eq!(PieceType::from_char('x'), None);
+    assert_eq!(PieceType::from_char('1'), None);
+}
+
+#[test]
+fn test_piece_type_values() {
+    assert_eq!(PieceType::Pawn.value(), 100);
+    assert_eq!(PieceType::Knight.value(), 300);
+    assert_eq!(PieceType::Bishop.value(), 320);
+    assert_eq!(PieceType::Rook.value(), 500);
+    assert_eq!(PieceType::Queen.value(), 900);
+    assert!(PieceType::King.value() > 2 * PieceType::Queen.value());
+}
+
+#[test]
+fn test_game_display() {
+    let game = Game::init();
+    let output = format!("{game}");
+    assert!(output.contains("FEN: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+    assert!(output.contains("8 | bR | bN | bB | bQ | bK | bB | bN | bR |"));
+    assert!(output.starts_with(&game.board.render()));
+}
+
+#[test]
+fn test_perft_leaves_position_unchanged() {
+    let mut game = Game::init();
+    let fen = game.get_fen();
+    assert_eq!(game.perft(0), 1);
+    assert_eq!(game.perft(2), 400);
+    assert_eq!(game.get_fen(), fen);
+    assert!(!game.undo_move());
+}
+
+#[test]
+fn test_king_square_matches_king_position() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/8/8/3k4/8/3K4/8/8 w - - 0 1",
+        "7k/8/8/8/8/8/8/K7 b - - 0 1",
+        "8/8/8/8/8/8/8/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        for is_white in [true, false] {
+            let position = game.board.get_king_position(is_white);
+            match game.board.king_square(is_white) {
+                Some(square) => assert_eq!(square, position, "{}", fen),
+                None => assert_eq!(position, 65, "{}", fen),
+            }
+        }
+    }
+
+    // The black king is missing in the last position
+    assert_eq!(Game::init().board.king_square(false), Some(4));
+    let mut game = Game::init();
+    game.try_set_from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1")
+        .unwrap();
+    assert_eq!(game.board.king_square(false), None);
+
+    // Rebuilt bitboards agree with the hand-written start position
+    let mut game = Game::init();
+    game.try_set_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+        .unwrap();
+    assert_eq!(game.board.bitboard, Game::init().board.bitboard);
+
+    // The bitboards follow the king after it moves
+    let mut game = Game::init();
+    game.try_play_move("e2", "e4", "").unwrap();
+    game.try_play_move("e7", "e5", "").unwrap();
+    game.try_play_move("e1", "e2", "").unwrap();
+    assert_eq!(
+        game.board.king_square(true),
+        Some(position_helper::try_letter_to_index("e2").unwrap())
+    );
+}
+
+#[test]
+fn test_missing_king_is_terminal() {
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("8/8/8/3q4/8/3K4/8/8 b - - 0 1")
+        .unwrap();
+    assert!(engine.game.get_legal_moves(false).is_empty());
+    assert_eq!(engine.game.status(), GameStatus::Checkmate);
+    assert_eq!(engine.alpha_beta(2, -100000, 100000), -100000);
+
+    let best_move = engine.get_best_move(1);
+    assert_eq!(best_move.source, best_move.target);
+}
+
+#[test]
+fn test_captured_material() {
+    let mut game = Game::init();
+    assert_eq!(game.captured_material(), (vec![], vec![]));
+
+    // 1. e4 d5 2. exd5 Qxd5 3. Nc3 Qxa2 4. Rxa2
+    game.try_play_move("e2", "e4", "").unwrap();
+    game.try_play_move("d7", "d5", "").unwrap();
+    game.try_play_move("e4", "d5", "").unwrap();
+    game.try_play_move("d8", "d5", "").unwrap();
+    game.try_play_move("b1", "c3", "").unwrap();
+    game.try_play_move("d5", "a2", "").unwrap();
+    game.try_play_move("a1", "a2", "").unwrap();
+
+    let (white_captured, black_captured) = game.captured_material();
+    assert_eq!(white_captured, vec![PieceType::Pawn, PieceType::Pawn]);
+    assert_eq!(black_captured, vec![PieceType::Queen, PieceType::Pawn]);
+}
+
+#[test]
+fn test_set_from_moves() {
+    let mut game = Game::init();
+    game.set_from_moves(None, &["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"])
+        .unwrap();
+    assert_eq!(
+        game.get_fen(),
+        "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4"
+    );
+
+    let start = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+    assert!(game.set_from_moves(Some(start), &["e2e4", "e8d7"]).is_ok());
+    assert_eq!(game.get_fen(), "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2");
+
+    assert_eq!(
+        game.set_from_moves(None, &["e2e4", "e7e5", "e4e5"]),
+        Err(MoveError::IllegalMove {
+            index: 2,
+            notation: "e4e5".to_string()
+        })
+    );
+    assert_eq!(
+        game.set_from_moves(None, &["e2e4", "e9e5"]),
+        Err(MoveError::InvalidNotation {
+            index: 1,
+            notation: "e9e5".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_castling_execution() {
+    let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+    // (white, king source, king target, rook source, rook target)
+    let cases = [
+        (true, "e1", "g1", "h1", "f1"),
+        (true, "e1", "c1", "a1", "d1"),
+        (false, "e8", "g8", "h8", "f8"),
+        (false, "e8", "c8", "a8", "d8"),
+    ];
+
+    for (is_white, king_source, king_target, rook_source, rook_target) in cases {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        if !is_white {
+            assert!(game.try_play_move("a2", "a3", "").is_ok());
+        }
+        assert!(game.try_play_move(king_source, king_target, "").is_ok());
+
+        let color = if is_white { WHITE_BIT } else { 0 };
+        let index = |square: &str| position_helper::try_letter_to_index(square).unwrap() as usize;
+        assert_eq!(
+            game.board.state[index(king_target)],
+            PIECE_BIT + color + KING
+        );
+        assert_eq!(
+            game.board.state[index(rook_target)],
+            PIECE_BIT + color + ROOK
+        );
+        assert_eq!(game.board.state[index(king_source)], 0);
+        assert_eq!(game.board.state[index(rook_source)], 0);
+
+        // Bitboards are indexed from a1, so the square index is flipped vertically
+        let rook_bitboard = if is_white { 1 } else { 7 };
+        let rook_bits = game.board.bitboard[rook_bitboard];
+        assert_ne!(rook_bits & (1u64 << (index(rook_target) ^ 56)), 0);
+        assert_eq!(rook_bits & (1u64 << (index(rook_source) ^ 56)), 0);
+        assert_eq!(
+            game.board.king_square(is_white),
+            Some(index(king_target) as u8)
+        );
+
+        let mut synced = game.board.clone();
+        synced.sync_bitboards();
+        assert_eq!(game.board.bitboard, synced.bitboard);
+    }
+}
+
+#[test]
+fn test_castling_without_rook_is_rejected() {
+    // The castling rights claim a rook on h1 that is not there
+    let fen = "4k3/8/8/8/8/8/8/4K1N1 w K - 0 1";
+    let mut game = Game::init();
+    game.try_set_from_fen(fen).unwrap();
+    let board_before = game.board.clone();
+
+    assert!(!game.play_move(move_from_squares("e1", "g1"), false));
+    assert_eq!(game.get_fen(), fen);
+    assert_eq!(game.board.state, board_before.state);
+    assert_eq!(game.board.bitboard, board_before.bitboard);
+    assert!(!game.undo_move());
+}
+
+const KNIGHT_DIRECTIONS: [(i16, i16); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const ROOK_DIRECTIONS: [(i16, i16); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const BISHOP_DIRECTIONS: [(i16, i16); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// Slow reference for the squares reached from `square` by stepping in each (row, col)
+/// direction. Sliding pieces keep going until they leave the board or hit a piece, which
+/// is the first square in every direction when `occupied` is set.
+fn reference_targets(
+    square: u8,
+    directions: &[(i16, i16)],
+    sliding: bool,
+    occupied: bool,
+) -> HashSet<u8> {
+    let mut targets = HashSet::new();
+    for (row_step, col_step) in directions {
+        let mut row = (square / 8) as i16 + row_step;
+        let mut col = (square % 8) as i16 + col_step;
+        while (0..8).contains(&row) && (0..8).contains(&col) {
+            targets.insert((row * 8 + col) as u8);
+            if !sliding || occupied {
+                break;
+            }
+            row += row_step;
+            col += col_step;
+        }
+    }
+    targets
+}
+
+fn generated_targets(piece_bits: u8, square: u8, board: &Board) -> HashSet<u8> {
+    let piece = Piece::init_from_binary(piece_bits);
+    piece
+        .possible_moves(square, board)
+        .iter()
+        .map(|mv| mv.target)
+        .collect()
+}
+
+#[test]
+fn test_knight_and_king_targets_on_every_square() {
+    let mut board = Board::init();
+    board.castling = 0;
+    let king_directions: Vec<(i16, i16)> = ROOK_DIRECTIONS
+        .iter()
+        .chain(BISHOP_DIRECTIONS.iter())
+        .copied()
+        .collect();
+
+    for square in 0..64u8 {
+        for color in [0, WHITE_BIT] {
+            assert_eq!(
+                generated_targets(PIECE_BIT + color + KNIGHT, square, &board),
+                reference_targets(square, &KNIGHT_DIRECTIONS, false, false),
+                "knight on {}",
+                position_helper::index_to_letter(square)
+            );
+            assert_eq!(
+                generated_targets(PIECE_BIT + color + KING, square, &board),
+                reference_targets(square, &king_directions, false, false),
+                "king on {}",
+                position_helper::index_to_letter(square)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_pawn_capture_targets_on_every_square() {
+    // Promotions are handled separately, so skip the squares a pawn promotes from
+    for (is_white, rows) in [(true, 2..7u8), (false, 1..6u8)] {
+        let color = if is_white { WHITE_BIT } else { 0 };
+        let enemy_color = WHITE_BIT - color;
+        let forward: i16 = if is_white { -1 } else { 1 };
+        for row in rows {
+            for col in 0..8u8 {
+                let square = row * 8 + col;
+                let mut board = Board::init();
+                // Fill every square in front of the pawn with enemies so it can only capture
+                let front_row = (row as i16 + forward) as u8;
+                for front_col in 0..8u8 {
+                    board.state[(front_row * 8 + front_col) as usize] =
+                        PIECE_BIT + enemy_color + KNIGHT;
+                }
+                board.state[square as usize] = PIECE_BIT + color + PAWN_BIT;
+
+                assert_eq!(
+                    generated_targets(PIECE_BIT + color + PAWN_BIT, square, &board),
+                    reference_targets(square, &[(forward, -1), (forward, 1)], false, false),
+                    "{} pawn on {}",
+                    if is_white { "white" } else { "black" },
+                    position_helper::index_to_letter(square)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sliding_targets_on_empty_and_full_boards() {
+    let queen_directions: Vec<(i16, i16)> = ROOK_DIRECTIONS
+        .iter()
+        .chain(BISHOP_DIRECTIONS.iter())
+        .copied()
+        .collect();
+    let pieces = [
+        (ROOK, &ROOK_DIRECTIONS[..]),
+        (BISHOP, &BISHOP_DIRECTIONS[..]),
+        (QUEEN, &queen_directions[..]),
+    ];
+
+    for square in 0..64u8 {
+        for color in [0, WHITE_BIT] {
+            let enemy_color = WHITE_BIT - color;
+            let empty_board = Board::init();
+            let mut enemy_board = Board::init();
+            let mut friendly_board = Board::init();
+            for other in 0..64usize {
+                if other != square as usize {
+                    enemy_board.state[other] = PIECE_BIT + enemy_color + KNIGHT;
+                    friendly_board.state[other] = PIECE_BIT + color + KNIGHT;
+                }
+            }
+
+            for (piece_bits, directions) in pieces {
+                let piece_bits = PIECE_BIT + color + piece_bits;
+                let name = position_helper::index_to_letter(square);
+                assert_eq!(
+                    generated_targets(piece_bits, square, &empty_board),
+                    reference_targets(square, directions, true, false),
+                    "{} on {} (empty board)",
+                    piece_bits,
+                    name
+                );
+                assert_eq!(
+                    generated_targets(piece_bits, square, &enemy_board),
+                    reference_targets(square, directions, true, true),
+                    "{} on {} (enemy pieces everywhere)",
+                    piece_bits,
+                    name
+                );
+                assert!(generated_targets(piece_bits, square, &friendly_board).is_empty());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_engine_builder() {
+    let engine = EngineBuilder::new().build();
+    assert_eq!(engine.search_depth(), cherris::engine::DEFAULT_SEARCH_DEPTH);
+    assert_eq!(engine.skill_level(), cherris::engine::MAX_SKILL_LEVEL);
+    assert_eq!(engine.game.get_fen(), Game::init().get_fen());
+
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+    let mut game = Game::init();
+    game.try_set_from_fen(fen).unwrap();
+    let build = || {
+        EngineBuilder::new()
+            .game(game.clone())
+            .depth(1)
+            .skill_level(0)
+            .seed(3)
+            .build()
+    };
+    let mut engine = build();
+    assert_eq!(engine.search_depth(), 1);
+    assert_eq!(engine.skill_level(), 0);
+    assert_eq!(engine.game.get_fen(), fen);
+
+    // The seeded engine plays like one configured through the setters
+    let mut reference_engine = Engine::init_from_game(game.clone());
+    reference_engine.set_search_depth(1);
+    reference_engine.set_skill_level(0);
+    reference_engine.set_seed(3);
+    let mv = engine.get_best_move_default();
+    assert_eq!(mv, reference_engine.get_best_move_default());
+    assert_eq!(mv, build().get_best_move_default());
+}
+
+#[test]
+fn test_pawn_double_push_only_from_home_rank() {
+    let targets = |fen: &str, square: &str| -> HashSet<String> {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        game.get_pseudolegal_moves(square.to_string())
+            .into_iter()
+            .collect()
+    };
+    let squares = |squares: &[&str]| -> HashSet<String> {
+        squares.iter().map(|square| square.to_string()).collect()
+    };
+
+    // Pawns on their home rank can push one or two squares
+    let fen = "4k3/3p4/8/8/8/8/4P3/4K3 w - - 0 1";
+    assert_eq!(targets(fen, "e2"), squares(&["e3", "e4"]));
+    let fen = "4k3/3p4/8/8/8/8/4P3/4K3 b - - 0 1";
+    assert_eq!(targets(fen, "d7"), squares(&["d6", "d5"]));
+
+    // Pawns that have already moved only push one square
+    let fen = "4k3/8/3p4/8/8/4P3/8/4K3 w - - 0 1";
+    assert_eq!(targets(fen, "e3"), squares(&["e4"]));
+    let fen = "4k3/8/3p4/8/8/4P3/8/4K3 b - - 0 1";
+    assert_eq!(targets(fen, "d6"), squares(&["d5"]));
+
+    // A piece directly in front blocks both pushes
+    let fen = "4k3/3p4/3N4/8/8/4n3/4P3/4K3 w - - 0 1";
+    assert!(targets(fen, "e2").is_empty());
+    let fen = "4k3/3p4/3N4/8/8/4n3/4P3/4K3 b - - 0 1";
+    assert!(targets(fen, "d7").is_empty());
+}
+
+#[test]
+fn test_epd_records() {
+    let records = [
+        "4k3/8/8/3q4/8/8/8/3QK3 w - - bm Qxd5; id \"free queen\";",
+        "4k3/8/8/8/8/2n5/8/1R2K3 b - - hmvc 3; fmvn 20; bm Nxb1; id \"free rook\";",
+    ];
+    for record in records {
+        let mut engine = Engine::init();
+        let ops = engine.game.set_from_epd(record).unwrap();
+        assert_eq!(ops.best_moves.len(), 1);
+        let best_move = engine.get_best_move(1);
+        assert_eq!(engine.game.move_to_san(best_move), ops.best_moves[0]);
+    }
+
+    let mut game = Game::init();
+    let ops = game.set_from_epd(records[1]).unwrap();
+    assert_eq!(ops.id, Some("free rook".to_string()));
+    assert_eq!(ops.operations[0], ("hmvc".to_string(), "3".to_string()));
+    assert_eq!(game.get_fen(), "4k3/8/8/8/8/2n5/8/1R2K3 b - - 3 20");
+    assert_eq!(
+        game.get_epd(),
+        "4k3/8/8/8/8/2n5/8/1R2K3 b - - hmvc 3; fmvn 20;"
+    );
+
+    // Invalid records are rejected without touching the game
+    let fen = game.get_fen();
+    assert_eq!(
+        game.set_from_epd("4k3/8/8/8/8/8/8/4K3 w"),
+        Err(FenError::MissingField("castling"))
+    );
+    assert!(matches!(
+        game.set_from_epd("4k3/8/8/8/8/8/4K3 w - -"),
+        Err(FenError::InvalidBoard(_))
+    ));
+    assert!(matches!(
+        game.set_from_epd("4k3/8/8/8/8/8/8/4K3 w - - hmvc x;"),
+        Err(FenError::InvalidOperation(_))
+    ));
+    // Castling rights must be listed once each, in the order KQkq
+    for castling in ["KKq", "qK", "kQ", "K-"] {
+        let record = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} -", castling);
+        assert_eq!(
+            game.set_from_epd(&record),
+            Err(FenError::InvalidCastling(castling.to_string()))
+        );
+    }
+    assert_eq!(game.get_fen(), fen);
+    assert!(game.set_from_epd("r3k2r/8/8/8/8/8/8/R3K2R w Kq -").is_ok());
+}
+
+#[test]
+fn test_undo_moves() {
+    let mut game = Game::init();
+    game.try_play_move("e2", "e4", "").unwrap();
+    game.try_play_move("e7", "e5", "").unwrap();
+    let fen = game.get_fen();
+    let board = game.board.clone();
+
+    game.try_play_move("g1", "f3", "").unwrap();
+    game.try_play_move("b8", "c6", "").unwrap();
+    assert_eq!(game.undo_moves(2), 2);
+    assert_eq!(game.get_fen(), fen);
+    assert_eq!(game.board.bitboard, board.bitboard);
+
+    // Only the moves in the history can be undone
+    assert_eq!(game.undo_moves(5), 2);
+    assert_eq!(game.get_fen(), Game::init().get_fen());
+    assert_eq!(game.undo_moves(1), 0);
+}
+
+#[test]
+fn test_checked_king_square() {
+    let mut game = Game::init();
+    assert!(!game.is_in_check());
+    assert_eq!(game.checked_king_square(), None);
+
+    // 1. e4 f5 2. Qh5+
+    game.set_from_moves(None, &["e2e4", "f7f5", "d1h5"])
+        .unwrap();
+    assert!(game.is_in_check());
+    assert_eq!(
+        game.checked_king_square(),
+        Some(position_helper::try_letter_to_index("e8").unwrap())
+    );
+
+    game.try_play_move("g7", "g6", "").unwrap();
+    assert_eq!(game.checked_king_square(), None);
+}
+
+#[test]
+fn test_attacks_from_queen_in_center() {
+    let mut game = Game::init();
+    game.try_set_from_fen("4k3/8/3p4/8/3Q4/8/5P2/4K3 w - - 0 1")
+        .unwrap();
+    let attacked: HashSet<String> = game
+        .attacks_from(position_helper::try_letter_to_index("d4").unwrap())
+        .into_iter()
+        .map(position_helper::index_to_letter)
+        .collect();
+
+    // Stops on the enemy pawn on d6 and the defended friendly pawn on f2
+    let expected: HashSet<String> = [
+        "d5", "d6", "d3", "d2", "d1", "c4", "b4", "a4", "e4", "f4", "g4", "h4", "e5", "f6", "g7",
+        "h8", "c5", "b6", "a7", "e3", "f2", "c3", "b2", "a1",
+    ]
+    .iter()
+    .map(|square| square.to_string())
+    .collect();
+    assert_eq!(attacked, expected);
+
+    // The pawn attacks diagonally even with nothing to capture, and never straight ahead
+    let pawn_attacks = game.attacks_from(position_helper::try_letter_to_index("f2").unwrap());
+    assert_eq!(
+        pawn_attacks.into_iter().collect::<HashSet<u8>>(),
+        HashSet::from([
+            position_helper::try_letter_to_index("e3").unwrap(),
+            position_helper::try_letter_to_index("g3").unwrap(),
+        ])
+    );
+    let empty_square = position_helper::try_letter_to_index("e4").unwrap();
+    assert!(game.attacks_from(empty_square).is_empty());
+}
+
+/// Cross-checks the move generator against `Game::attacks_from`, which walks attacks separately.
+/// Every capture must land on an attacked enemy square and every attacked enemy square must be
+/// capturable; non-pawn moves must cover exactly the attacked squares not held by friendly
+/// pieces, apart from castling. Panics with the symmetric difference of the two sets otherwise.
+fn assert_generators_agree(game: &Game) {
+    let board = &game.board;
+    let moves = game.get_all_moves_for_color(game.white_turn);
+    for source in 0..64u8 {
+        if board.piece_color(source) != Some(game.white_turn) {
+            continue;
+        }
+        let piece = Piece::init_from_binary(board.state[source as usize]);
+        let attacked: HashSet<u8> = game.attacks_from(source).into_iter().collect();
+        let generated: HashSet<u8> = moves
+            .iter()
+            .filter(|mv| mv.source == source)
+            .map(|mv| mv.target)
+            .collect();
+
+        let (generated, expected): (HashSet<u8>, HashSet<u8>) = match piece.class {
+            PieceType::Pawn => {
+                // Only diagonal pawn moves are captures
+                let captures = generated
+                    .into_iter()
+                    .filter(|target| target % 8 != source % 8)
+                    .collect();
+                let capturable = attacked
+                    .into_iter()
+                    .filter(|&target| {
+                        board.is_enemy(target, game.white_turn)
+                            || (board.en_passant != 0 && target == board.en_passant)
+                    })
+                    .collect();
+                (captures, capturable)
+            }
+            _ => {
+                let castling = |target: &u8| {
+                    piece.class == PieceType::King && (*target as i16 - source as i16).abs() == 2
+                };
+                let without_castling = generated.into_iter().filter(|x| !castling(x)).collect();
+                let reachable = attacked
+                    .into_iter()
+                    .filter(|&target| board.piece_color(target) != Some(game.white_turn))
+                    .collect();
+                (without_castling, reachable)
+            }
+        };
+
+        if generated != expected {
+            let squares = |set: HashSet<&u8>| -> Vec<String> {
+                let mut squares: Vec<String> = set
+                    .into_iter()
+                    .map(|square| position_helper::index_to_letter(*square))
+                    .collect();
+                squares.sort();
+                squares
+            };
+            panic!(
+                "{:?} on {} in {}: only generated {:?}, only attacked {:?}",
+                piece.class,
+                position_helper::index_to_letter(source),
+                game.get_fen(),
+                squares(generated.difference(&expected).collect()),
+                squares(expected.difference(&generated).collect()),
+            );
+        }
+    }
+}
+
+/// Returns every position reachable from `game` in at most `depth` legal moves.
+fn positions_up_to(game: &Game, depth: u8) -> Vec<Game> {
+    let mut positions = vec![game.clone()];
+    if depth == 0 {
+        return positions;
+    }
+    for mv in game.get_legal_moves(game.white_turn) {
+        let mut child = game.clone();
+        child.play_move_ob(mv);
+        positions.append(&mut positions_up_to(&child, depth - 1));
+    }
+    positions
+}
+
+#[test]
+fn test_generators_agree_on_perft_positions() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        // Pawns about to promote, with nothing to capture on a8
+        "4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        for position in positions_up_to(&game, 2) {
+            assert_generators_agree(&position);
+        }
+    }
+}
+
+#[test]
+fn test_promotions_for_both_colors() {
+    let promotion_moves = |fen: &str, source: &str| -> HashSet<(String, u8)> {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        let source = position_helper::try_letter_to_index(source).unwrap();
+        game.get_legal_moves(game.white_turn)
+            .into_iter()
+            .filter(|mv| mv.source == source)
+            .map(|mv| (position_helper::index_to_letter(mv.target), mv.promotion))
+            .collect()
+    };
+    let expected = |targets: &[&str], color: u8| -> HashSet<(String, u8)> {
+        let mut moves = HashSet::new();
+        for target in targets {
+            for piece_bits in [QUEEN, ROOK, BISHOP, KNIGHT] {
+                moves.insert((target.to_string(), PIECE_BIT + color + piece_bits));
+            }
+        }
+        moves
+    };
+
+    // Pushes and captures onto the last rank promote to a piece of the pawn's color
+    let fen = "r3k3/1P6/8/8/8/8/6p1/4K2N w - - 0 1";
+    assert_eq!(
+        promotion_moves(fen, "b7"),
+        expected(&["b8", "a8"], WHITE_BIT)
+    );
+    let fen = "r3k3/1P6/8/8/8/8/6p1/4K2N b - - 0 1";
+    assert_eq!(promotion_moves(fen, "g2"), expected(&["g1", "h1"], 0));
+
+    // Playing a black promotion puts a black piece on the board
+    let mut game = Game::init();
+    game.try_set_from_fen(fen).unwrap();
+    assert!(game.play_move(Move::from_uci("g2h1n").unwrap(), true));
+    assert_eq!(game.get_fen(), "r3k3/1P6/8/8/8/8/8/4K2n w - - 0 2");
+}
+
+/// Runs the terminal client with the given arguments, feeding it `input` on stdin.
+fn run_play_cli(args: &[&str], input: &str) -> String {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_play"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_san_to_move() {
+    let mut game = Game::init();
+    assert_eq!(game.san_to_move("Nf3"), Some(move_from_squares("g1", "f3")));
+    assert_eq!(game.san_to_move("e4"), Some(move_from_squares("e2", "e4")));
+    assert_eq!(game.san_to_move("e5"), None);
+
+    game.try_set_from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1")
+        .unwrap();
+    assert_eq!(game.san_to_move("0-0"), Some(move_from_squares("e1", "g1")));
+    let rook_check = Some(move_from_squares("a1", "a8"));
+    assert_eq!(game.san_to_move("Ra8+"), rook_check);
+    assert_eq!(game.san_to_move("Ra8"), rook_check);
+}
+
+#[test]
+fn test_play_cli() {
+    // The human mates straight away
+    let output = run_play_cli(&["--fen", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"], "Ra8#\n");
+    assert!(output.contains("Checkmate, White wins"));
+
+    // Illegal moves are rejected and undo takes back the engine's reply too
+    let start_fen = Game::init().get_fen();
+    let output = run_play_cli(&["--depth", "1"], "e2e5\ne2e4\nundo\nfen\nquit\n");
+    assert!(output.contains("Illegal move: e2e5"));
+    assert!(output.contains("Engine plays"));
+    // Each prompt is followed by the response to one input line
+    let responses: Vec<&str> = output.split("> ").collect();
+    assert_eq!(responses[4].trim(), start_fen);
+}
+
+#[test]
+fn test_move_flags() {
+    let find_move = |game: &Game, source: &str, target: &str| -> Move {
+        let wanted = move_from_squares(source, target);
+        game.get_legal_moves(game.white_turn)
+            .into_iter()
+            .find(|mv| *mv == wanted)
+            .unwrap()
+    };
+
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k2r/pppp1ppp/8/3Pp3/8/8/1PP2PPP/R3K2R w KQkq e6 0 1")
+        .unwrap();
+    let en_passant = find_move(&game, "d5", "e6");
+    assert!(en_passant.is_en_passant() && en_passant.is_capture());
+    assert!(!en_passant.is_castle());
+
+    let castle = find_move(&game, "e1", "g1");
+    assert!(castle.is_castle() && !castle.is_capture());
+    assert!(find_move(&game, "e1", "c1").is_castle());
+
+    let double_push = find_move(&game, "b2", "b4");
+    assert!(double_push.is_double_push());
+    let single_push = find_move(&game, "b2", "b3");
+    assert_eq!(single_push.flags, 0);
+
+    let rook_capture = find_move(&game, "a1", "a7");
+    assert_eq!(rook_capture.flags, CAPTURE_FLAG);
+
+    // Hand-built moves compare equal to generated ones regardless of flags
+    assert_eq!(castle, move_from_squares("e1", "g1"));
+
+    game.try_set_from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1")
+        .unwrap();
+    let promotions: Vec<Move> = game
+        .get_legal_moves(true)
+        .into_iter()
+        .filter(|mv| mv.source == position_helper::try_letter_to_index("a7").unwrap())
+        .collect();
+    assert_eq!(promotions.len(), 8);
+    assert!(promotions.iter().all(|mv| mv.is_promotion()));
+    assert_eq!(promotions.iter().filter(|mv| mv.is_capture()).count(), 4);
+}
+
+#[test]
+fn test_quiescence_searches_check_evasions() {
+    // White is in check and a queen down, but the only evasion wins the queen back
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("4k3/7p/8/8/8/8/P3q3/4K3 w - - 0 1")
+        .unwrap();
+    let static_eval = engine.evaluate(&engine.game.board.clone());
+    assert!(static_eval < -800);
+    let score = engine.quiescence(-100000, 100000);
+    assert!(score.abs() < 100, "score was {}", score);
+    assert_eq!(engine.game.get_fen(), "4k3/7p/8/8/8/8/P3q3/4K3 w - - 0 1");
+
+    // With the queen protected there is no evasion, which is a loss rather than a stand pat
+    engine
+        .game
+        .try_set_from_fen("4k3/7p/8/8/8/3p4/P3q3/4K3 w - - 0 1")
+        .unwrap();
+    assert!(engine.game.get_legal_moves(true).is_empty());
+    assert_eq!(engine.quiescence(-100000, 100000), -100000);
+}
+
+#[test]
+fn test_gives_check() {
+    let mut game = Game::init();
+    game.try_set_from_fen("4k3/8/8/8/8/4B3/8/R3K2R w - - 0 1")
+        .unwrap();
+    // Direct check along the a-file onto the back rank
+    assert!(game.gives_check(&move_from_squares("a1", "a8")));
+    // Not a check
+    assert!(!game.gives_check(&move_from_squares("a1", "a2")));
+
+    // Moving the bishop off the e-file uncovers the rook behind it
+    game.try_set_from_fen("4k3/8/8/8/8/4B3/8/4RK2 w - - 0 1")
+        .unwrap();
+    assert!(game.gives_check(&move_from_squares("e3", "c5")));
+    assert!(!game.gives_check(&move_from_squares("e1", "d1")));
+}
+
+#[test]
+fn test_incremental_material_matches_recomputation() {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let start_positions = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        // Pawns one step from promotion on both sides, with an en passant capture available
+        "4k3/1P5p/8/3pP3/8/8/6p1/4K3 w - d6 0 1",
+    ];
+    let mut rng = StdRng::seed_from_u64(896);
+    let mut game = Game::init();
+    for fen in start_positions {
+        for _ in 0..10 {
+            game.try_set_from_fen(fen).unwrap();
+            for _ in 0..150 {
+                let moves = game.get_legal_moves(game.white_turn);
+                if moves.is_empty() {
+                    break;
+                }
+                let mv = moves[rng.gen_range(0..moves.len())];
+                assert!(game.play_move_ob(mv));
+
+                let fen = game.get_fen();
+                let mut recomputed = game.board.clone();
+                recomputed.recompute_material();
+                assert_eq!(game.board.material, recomputed.material, "{}", fen);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_back_rank_penalty() {
+    let mut engine = Engine::init();
+    // Both kings are boxed in behind their pawns with the heavy pieces still on the board
+    engine
+        .game
+        .try_set_from_fen("r2q1rk1/5ppp/8/8/8/8/5PPP/R2Q1RK1 w - - 0 1")
+        .unwrap();
+    let no_luft = engine.evaluate(&engine.game.board.clone());
+    assert_eq!(no_luft, 0);
+
+    // h3 gives the white king an escape square, and h2 and h3 are worth the same to a pawn
+    engine
+        .game
+        .try_set_from_fen("r2q1rk1/5ppp/8/8/8/7P/5PP1/R2Q1RK1 w - - 0 1")
+        .unwrap();
+    let luft = engine.evaluate(&engine.game.board.clone());
+    assert!(luft > no_luft, "{} <= {}", luft, no_luft);
+
+    // Without the heavy pieces there is nothing to mate with, so luft makes no difference
+    engine
+        .game
+        .try_set_from_fen("n1b2bk1/5ppp/8/8/8/8/5PPP/N1B2BK1 w - - 0 1")
+        .unwrap();
+    let endgame_no_luft = engine.evaluate(&engine.game.board.clone());
+    engine
+        .game
+        .try_set_from_fen("n1b2bk1/5ppp/8/8/8/7P/5PP1/N1B2BK1 w - - 0 1")
+        .unwrap();
+    assert_eq!(engine.evaluate(&engine.game.board.clone()), endgame_no_luft);
+}
+
+#[test]
+fn test_legal_moves_map() {
+    let game = Game::init();
+    let moves_map = game.legal_moves_map();
+    assert_eq!(moves_map["b1"], vec!["a3", "c3"]);
+    assert_eq!(moves_map["e2"], vec!["e3", "e4"]);
+    // 8 pawns and 2 knights can move
+    assert_eq!(moves_map.len(), 10);
+    assert_eq!(moves_map.values().map(Vec::len).sum::<usize>(), 20);
+    assert!(!moves_map.contains_key("e1"));
+}
+
+#[test]
+fn test_fen_without_move_clocks() {
+    let mut game = Game::init();
+    let four_fields = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6";
+    assert_eq!(game.try_set_from_fen(four_fields), Ok(()));
+    assert_eq!(game.get_fen(), format!("{} 0 1", four_fields));
+
+    // The trait method accepts the short form as well
+    game.try_set_from_fen("8/8/8/4k3/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(game.get_fen(), "8/8/8/4k3/8/8/8/4K3 b - - 0 1");
+
+    // Full records keep their clocks, and bad clocks are rejected without changing the game
+    let full = "8/8/8/4k3/8/8/8/4K3 w - - 12 40";
+    assert_eq!(game.try_set_from_fen(full), Ok(()));
+    assert_eq!(game.get_fen(), full);
+    assert_eq!(
+        game.try_set_from_fen("8/8/8/4k3/8/8/8/4K3 w - - x 1"),
+        Err(FenError::InvalidMoveClock("x".to_string()))
+    );
+    assert_eq!(game.get_fen(), full);
+}
+
+#[test]
+fn test_is_legal_matches_legal_move_generation() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        for position in positions_up_to(&game, 1) {
+            let legal_moves = position.get_legal_moves(position.white_turn);
+            // Pseudolegal moves of both sides, so pinned pieces and moves out of turn are covered
+            let mut candidates = position.get_all_moves_for_color(true);
+            candidates.append(&mut position.get_all_moves_for_color(false));
+            for mv in candidates {
+                assert_eq!(
+                    position.is_legal(&mv),
+                    legal_moves.contains(&mv),
+                    "{:?} in {}",
+                    mv,
+                    position.get_fen()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_bare_kings_short_circuit_the_search() {
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("8/8/3k4/8/8/4K3/8/8 w - - 0 1")
+        .unwrap();
+    assert_eq!(engine.evaluate(&engine.game.board.clone()), 0);
+    // The search stops at the node itself, however deep it was asked to go
+    let mut nodes_at_depth = |depth| {
+        let before = engine.nodes_searched();
+        assert_eq!(engine.alpha_beta(depth, -100000, 100000), 0);
+        engine.nodes_searched() - before
+    };
+    assert_eq!(nodes_at_depth(6), nodes_at_depth(1));
+
+    // At the root, each king move is scored without searching below it
+    let king_moves = engine.game.legal_moves_for(engine.game.turn()).len() as i64;
+    engine.get_best_move(3);
+    assert!(engine.nodes_searched() <= king_moves);
+}
+
+#[test]
+fn test_search_iterations() {
+    let mut engine = Engine::init();
+    assert!(engine.search_iterations().is_empty());
+    let best_move = engine.get_best_move_iterative(2);
+    assert!(engine.game.is_legal(&best_move));
+
+    let iterations = engine.search_iterations();
+    assert_eq!(iterations.len(), 2);
+    assert_eq!(iterations[0].0, 1);
+    assert_eq!(iterations[1].0, 2);
+    // A deeper iteration visits more nodes and, with them, takes longer
+    assert!(iterations[1].2 > iterations[0].2);
+    assert!(iterations[1].1 >= iterations[0].1);
+
+    // The structured result comes from the deepest iteration, with every iteration counted
+    let result = engine.search_iterative(2);
+    assert_eq!(result.best_move, Some(best_move));
+    assert_eq!(result.depth, 2);
+    let iterations = engine.search_iterations();
+    assert_eq!(iterations.len(), 2);
+    assert_eq!(result.nodes, iterations[0].2 + iterations[1].2);
+    assert_eq!(result.elapsed, iterations[0].1 + iterations[1].1);
+
+    let mut engine = EngineBuilder::new().depth(2).build();
+    let result = engine.search_default();
+    assert_eq!(result.depth, 2);
+    assert_eq!(result.best_move, Some(engine.get_best_move_default()));
+}
+
+#[test]
+fn test_search_result() {
+    let mut engine = Engine::init();
+    let result = engine.search(2);
+    let best_move = result.best_move.unwrap();
+    assert!(engine.game.is_legal(&best_move));
+    assert_eq!(result.depth, 2);
+    assert!(result.nodes > 0);
+    assert!(result.elapsed > Duration::ZERO);
+    assert_eq!(result.pv[0], best_move);
+    // Every move of the expected line can be played in turn
+    let mut game = engine.game.clone();
+    for mv in &result.pv {
+        assert!(game.is_legal(mv));
+        game.play_move_ob(*mv);
+    }
+
+    // Back rank mate
+    engine
+        .game
+        .try_set_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")
+        .unwrap();
+    let result = engine.search(1);
+    let best_move = result.best_move.unwrap();
+    assert_eq!(best_move, Move::from_uci("a1a8").unwrap());
+    assert!(result.score > 10000);
+
+    // Checkmated, nothing to play
+    engine
+        .game
+        .try_set_from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1")
+        .unwrap();
+    let result = engine.search(1);
+    assert!(result.best_move.is_none());
+    assert!(result.pv.is_empty());
+}
+
+#[test]
+fn test_position_hash() {
+    let mut kingside_first = Game::init();
+    kingside_first
+        .set_from_moves(None, &["g1f3", "g8f6", "b1c3", "b8c6"])
+        .unwrap();
+    let mut queenside_first = Game::init();
+    queenside_first
+        .set_from_moves(None, &["b1c3", "b8c6", "g1f3", "g8f6"])
+        .unwrap();
+    assert_eq!(kingside_first.hash(), queenside_first.hash());
+    assert_ne!(kingside_first.hash(), Game::init().hash());
+
+    let hash_of = |fen: &str| game_from_fen(fen).hash();
+    let base = hash_of("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+    // The move clocks are not part of the position
+    assert_eq!(
+        base,
+        hash_of("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 5 9")
+    );
+    assert_ne!(
+        base,
+        hash_of("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w Kkq - 0 2")
+    );
+    assert_ne!(
+        base,
+        hash_of("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2")
+    );
+    assert_ne!(
+        base,
+        hash_of("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2")
+    );
+}
+
+#[test]
+fn test_random_move() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(904);
+
+    // Stalemate: no move to play, and the status says it is a draw rather than a loss
+    let mut game = Game::init();
+    game.try_set_from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")
+        .unwrap();
+    assert_eq!(game.random_move(&mut rng, false), None);
+    assert_eq!(game.status(), GameStatus::Stalemate);
+    assert_eq!(game.status().as_str(), "stalemate");
+
+    // Kh8 is the only move that walks into a back-rank mate, Ra8#
+    game.try_set_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1")
+        .unwrap();
+    let random_moves = |rng: &mut StdRng, avoid_mate_in_one: bool| -> HashSet<String> {
+        (0..200)
+            .map(|_| game.random_move(rng, avoid_mate_in_one).unwrap())
+            .map(|mv| game.move_to_san(mv))
+            .collect()
+    };
+    assert!(random_moves(&mut rng, false).contains("Kh8"));
+    let safe_moves = random_moves(&mut rng, true);
+    assert!(!safe_moves.contains("Kh8"));
+    assert_eq!(safe_moves.len(), 7);
+}
+
+#[test]
+fn test_perft_with_callback() {
+    let mut game = Game::init();
+    game.try_set_from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1")
+        .unwrap();
+    let mut root_counts: Vec<(Move, u64)> = vec![];
+    let total = game.perft_with_callback(3, &mut |mv, nodes| root_counts.push((mv, nodes)));
+
+    assert_eq!(total, 2812);
+    assert_eq!(total, game.perft(3));
+    assert_eq!(root_counts.len(), game.get_legal_moves(true).len());
+    let summed: u64 = root_counts.iter().map(|(_, nodes)| nodes).sum();
+    assert_eq!(summed, total);
+    assert_eq!(game.get_fen(), "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+}
+
+#[test]
+fn test_rook_on_seventh_rank() {
+    let mut engine = Engine::init();
+    let mut evaluate = |fen: &str| engine.evaluate(&game_from_fen(fen).board);
+    let first_rank = evaluate("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+    let seventh_rank = evaluate("4k3/R7/8/8/8/8/8/4K3 w - - 0 1");
+    // The rook tables alone favour a7 by 15, the rest is the 7th rank bonus
+    let white_gain = seventh_rank - first_rank;
+    assert!(white_gain > 15, "{}", white_gain);
+
+    // The same holds for black on its 7th rank, which is the 2nd
+    let black_first_rank = evaluate("r3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    let black_seventh_rank = evaluate("4k3/8/8/8/8/8/r7/4K3 b - - 0 1");
+    let black_gain = black_first_rank - black_seventh_rank;
+    assert_eq!(black_gain, white_gain);
+}
+
+#[test]
+fn test_engine_personality() {
+    use cherris::engine::Personality;
+
+    // The default tables reward the central pawn pushes, the positional ones the knights.
+    // d4 and e4 score the same at this depth, as do Nc3 and Nf3, and the search keeps the
+    // first move of a tie, like a full-window search of every move.
+    let tied_best = |engine: &mut Engine| {
+        let scores = engine.root_move_scores(1);
+        let best = scores.iter().map(|&(_, score)| score).max().unwrap();
+        scores
+            .iter()
+            .filter(|&&(_, score)| score == best)
+            .map(|&(mv, _)| engine.game.move_to_san(mv))
+            .collect::<Vec<_>>()
+    };
+    let mut engine = EngineBuilder::new().build();
+    assert_eq!(engine.personality(), Personality::Aggressive);
+    assert_eq!(tied_best(&mut engine), ["d4", "e4"]);
+    let aggressive_move = engine.get_best_move(1);
+    assert_eq!(engine.game.move_to_san(aggressive_move), "d4");
+
+    engine.set_personality(Personality::Positional);
+    assert_eq!(tied_best(&mut engine), ["Nc3", "Nf3"]);
+    let positional_move = engine.get_best_move(1);
+    assert_eq!(engine.game.move_to_san(positional_move), "Nc3");
+
+    let engine = EngineBuilder::new()
+        .personality(Personality::Positional)
+        .build();
+    assert_eq!(engine.personality(), Personality::Positional);
+    assert_eq!(
+        Personality::from_name("aggressive"),
+        Some(Personality::Aggressive)
+    );
+    assert_eq!(Personality::from_name("reckless"), None);
+}
+
+#[test]
+fn test_search_stats_without_legal_moves() {
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")
+        .unwrap();
+    let best_move = engine.get_best_move(2);
+    assert_eq!((best_move.source, best_move.target), (0, 0));
+    assert_eq!(engine.nodes_searched(), 0);
+    assert_eq!(engine.cache_hit_rate(), 0f32);
+
+    engine.game = Game::init();
+    engine.get_best_move(1);
+    let rate = engine.cache_hit_rate();
+    assert!(rate.is_finite() && (0f32..=1f32).contains(&rate));
+}
+
+#[test]
+fn test_move_counters() {
+    let mut game = Game::init();
+    assert_eq!(game.ply(), 0);
+
+    game.set_from_moves(None, &["e2e4"]).unwrap();
+    assert_eq!(game.half_move_clock(), 0);
+    assert_eq!(game.full_move_number(), 1);
+    assert_eq!(game.ply(), 1);
+
+    game.set_from_moves(None, &["e2e4", "e7e5", "g1f3", "b8c6"])
+        .unwrap();
+    assert_eq!(game.half_move_clock(), 2);
+    assert_eq!(game.full_move_number(), 3);
+    assert_eq!(game.ply(), 4);
+}
+
+#[test]
+fn test_history_and_goto() {
+    let mut game = Game::init();
+    game.set_from_moves(None, &["e2e4", "e7e5", "g1f3", "b8c6"])
+        .unwrap();
+    let history = game.history().to_vec();
+    assert_eq!(history.len(), 4);
+    assert_eq!(
+        history[0],
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+
+    assert!(!game.goto(5));
+    assert_eq!(game.history().len(), 4);
+
+    assert!(game.goto(2));
+    assert_eq!(
+        game.get_fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+    );
+    assert_eq!(game.history(), &history[..2]);
+
+    assert!(game.goto(0));
+    assert_eq!(game.get_fen(), history[0]);
+    assert!(game.history().is_empty());
+}
+
+#[test]
+fn test_loading_fen_clears_history() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    let mut game = Game::init();
+    game.set_from_moves(None, &shuffle).unwrap();
+    assert_eq!(game.repetition_count(), 2);
+
+    game.try_set_from_fen(start).unwrap();
+    assert!(game.history().is_empty());
+    assert!(!game.goto(1));
+    assert_eq!(game.repetition_count(), 1);
+    assert!(!game.undo_move());
+
+    game.set_from_moves(None, &shuffle).unwrap();
+    game.try_set_from_fen(start).unwrap();
+    assert!(game.history().is_empty());
+
+    game.set_from_moves(None, &shuffle).unwrap();
+    assert!(game.set_from_simple_fen("4k3/8/8/8/8/8/8/4K3".to_string()));
+    assert!(game.history().is_empty());
+
+    game.set_from_moves(None, &shuffle).unwrap();
+    game.set_from_fen_direct(start).unwrap();
+    assert!(game.history().is_empty());
+
+    // Undoing still steps back through the moves played since the position was loaded
+    game.set_from_moves(None, &shuffle[..2]).unwrap();
+    assert!(game.undo_move());
+    assert_eq!(game.history().len(), 1);
+}
+
+#[test]
+fn test_fifty_move_rule_pressure() {
+    // White is winning, but after two more quiet moves black could claim a fifty-move draw
+    let fen = "7k/8/8/8/8/8/P7/K2Q4 w - - 98 80";
+    for depth in [1, 2] {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        let best_move = engine.get_best_move(depth);
+        assert_eq!(position_helper::index_to_letter(best_move.source), "a2");
+    }
+
+    let search = |fen: &str| {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        engine.alpha_beta(2, -100000, 100000)
+    };
+    // With the clock at the limit the losing side claims the draw, while the winning side
+    // plays on and pushes the pawn to reset the clock
+    assert_eq!(search("7k/8/8/8/8/8/P7/K2Q4 b - - 100 80"), 0);
+    assert!(search("7k/8/8/8/8/8/P7/K2Q4 w - - 100 80") > 0);
+    // The 75-move rule ends the game whoever is winning
+    assert_eq!(search("7k/8/8/8/8/8/P7/K2Q4 w - - 150 80"), 0);
+    // A mate on the hundredth half move still wins, as it does in Game::status
+    assert!(search("7k/8/6K1/8/8/8/8/R7 w - - 99 80") > 99000);
+    let mut game = Game::init();
+    game.try_set_from_fen("7k/8/6K1/8/8/8/8/R7 w - - 99 80")
+        .unwrap();
+    assert!(game.play_move_ob(Move::from_uci("a1a8").unwrap()));
+    assert_eq!(game.status(), GameStatus::Checkmate);
+    assert!(!game.can_claim_fifty_move_draw());
+    game.try_set_from_fen("7k/8/6K1/8/8/8/8/R7 b - - 100 80")
+        .unwrap();
+    assert!(game.can_claim_fifty_move_draw());
+    assert_eq!(game.status(), GameStatus::Ongoing);
+}
+
+#[test]
+fn test_pseudolegal_moves_from() {
+    let game = Game::init();
+    let e2 = position_helper::try_letter_to_index("e2").unwrap();
+    let moves = game.get_pseudolegal_moves_from(e2);
+    assert_eq!(moves.len(), 2);
+    assert!(moves.iter().all(|mv| mv.source == e2));
+    let double_push = moves.iter().find(|mv| mv.is_double_push()).unwrap();
+    assert_eq!(position_helper::index_to_letter(double_push.target), "e4");
+    // The string adapter keeps returning just the targets
+    let targets = game.get_pseudolegal_moves("e2".to_string());
+    assert_eq!(targets, vec!["e3", "e4"]);
+    assert!(game.get_pseudolegal_moves_from(30).is_empty());
+    assert!(game.get_pseudolegal_moves_from(64).is_empty());
+
+    let mut game = Game::init();
+    game.try_set_from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1")
+        .unwrap();
+    let b7 = position_helper::try_letter_to_index("b7").unwrap();
+    let promotions: Vec<u8> = game
+        .get_pseudolegal_moves_from(b7)
+        .iter()
+        .filter(|mv| mv.is_promotion())
+        .map(|mv| mv.promotion)
+        .collect();
+    assert_eq!(
+        promotions,
+        vec![
+            PIECE_BIT + WHITE_BIT + QUEEN,
+            PIECE_BIT + WHITE_BIT + ROOK,
+            PIECE_BIT + WHITE_BIT + BISHOP,
+            PIECE_BIT + WHITE_BIT + KNIGHT,
+        ]
+    );
+}
+
+#[test]
+fn test_automatic_draws() {
+    // 75-move rule: two more quiet moves bring the clock to 150
+    let mut game = Game::init();
+    let start = Some("4k3/8/8/8/8/8/8/R3K3 w - - 148 120");
+    game.set_from_moves(start, &["a1a2"]).unwrap();
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    game.set_from_moves(start, &["a1a2", "e8d8"]).unwrap();
+    assert_eq!(game.status(), GameStatus::SeventyFiveMoveRule);
+    assert!(game.status().is_draw());
+
+    // Fivefold repetition: the knights go out and back four times
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    let mut moves: Vec<&str> = vec![];
+    for cycle in 1..=4 {
+        moves.extend_from_slice(&shuffle);
+        game.set_from_moves(None, &moves).unwrap();
+        assert_eq!(game.repetition_count(), cycle + 1);
+        let expected = if cycle < 4 {
+            GameStatus::Ongoing
+        } else {
+            GameStatus::FivefoldRepetition
+        };
+        assert_eq!(game.status(), expected);
+    }
+    assert_eq!(game.status().as_str(), "fivefold_repetition");
+}
+
+#[test]
+fn test_board_builder() {
+    let square = |name: &str| position_helper::try_letter_to_index(name).unwrap();
+    let mut board = Board::empty();
+    board
+        .place(square("e1"), PieceType::King, true)
+        .place(square("a1"), PieceType::Rook, true)
+        .place(square("e8"), PieceType::King, false)
+        .place(square("h5"), PieceType::Queen, false)
+        .clear(square("h5"));
+
+    let mut game = Game::init();
+    game.board = board.clone();
+    assert_eq!(game.get_fen_simple(), "4k3/8/8/8/8/8/8/R3K3");
+    assert_eq!(game.board.get_castling_fen(), "-");
+
+    // The bitboards and material match a board set up from the FEN
+    game.try_set_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")
+        .unwrap();
+    assert_eq!(board.bitboard, game.board.bitboard);
+    assert_eq!(board.material, game.board.material);
+    assert_eq!(board.game_phase(), game.board.game_phase());
+
+    // Placing on an occupied square replaces the piece
+    board.place(square("a1"), PieceType::Knight, true);
+    assert_eq!(board.material, [300, 0]);
+    assert_eq!(board.bitboard[1], 0);
+}
+
+#[test]
+fn test_is_attacked() {
+    let square = |name: &str| position_helper::try_letter_to_index(name).unwrap();
+    let mut game = Game::init();
+    game.try_set_from_fen("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1")
+        .unwrap();
+
+    // The pawn attacks diagonally forward, not the square it pushes to
+    assert!(game.is_attacked(square("d5"), true));
+    assert!(game.is_attacked(square("f5"), true));
+    assert!(!game.is_attacked(square("e5"), true));
+    assert!(!game.is_attacked(square("a5"), true));
+    assert!(!game.is_attacked(square("d5"), false));
+
+    // Kings attack the squares around them
+    assert!(game.is_attacked(square("d7"), false));
+    assert!(game.is_attacked(square("e2"), true));
+    assert!(!game.is_attacked(square("h4"), false));
+}
+
+#[test]
+fn test_knight_outposts_and_rim() {
+    let mut engine = Engine::init();
+    let mut evaluate = |fen: &str| engine.evaluate(&game_from_fen(fen).board);
+    // d5 is defended by the e4 pawn, and with the black pawn on a7 nothing can chase it away
+    let outpost = evaluate("r3k2r/p7/8/3N4/4P3/8/8/R3K2R w - - 0 1");
+    // A black pawn on c7 can still kick the knight with c6, which the tables score the same
+    let no_outpost = evaluate("r3k2r/2p5/8/3N4/4P3/8/8/R3K2R w - - 0 1");
+    let rim = evaluate("r3k2r/p7/8/N7/4P3/8/8/R3K2R w - - 0 1");
+    assert!(outpost > no_outpost, "{} <= {}", outpost, no_outpost);
+    assert!(no_outpost > rim, "{} <= {}", no_outpost, rim);
+
+    // The same holds for black, with the board mirrored
+    let black_outpost = evaluate("r3k2r/8/8/4p3/3n4/8/P7/R3K2R b - - 0 1");
+    assert_eq!(black_outpost, -outpost);
+}
+
+#[test]
+fn test_kpk_oracle() {
+    let square = |name: &str| position_helper::try_letter_to_index(name).unwrap();
+    let won = |white_to_move: bool, wk: &str, bk: &str, wp: &str| {
+        kpk::kpk_is_won(white_to_move, square(wk), square(bk), square(wp))
+    };
+
+    // King on the sixth rank in front of its pawn wins whoever is to move
+    assert!(won(true, "e6", "e8", "e5"));
+    assert!(won(false, "e6", "e8", "e5"));
+    // The opposition decides with the king in front of the pawn
+    assert!(!won(true, "e5", "e7", "e4"));
+    assert!(won(false, "e5", "e7", "e4"));
+    // Rule of the square
+    assert!(won(true, "h1", "e5", "a5"));
+    assert!(!won(false, "h1", "e5", "a5"));
+    // A rook pawn is drawn once the defending king reaches the corner
+    assert!(!won(true, "g4", "h8", "h4"));
+    // The same position on the other wing
+    assert!(won(true, "d6", "d8", "d5"));
+    assert!(won(false, "d6", "d8", "d5"));
+
+    // The engine scores drawn endings from the oracle, here with black's pawn
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("7k/8/8/8/p3K3/8/8/8 b - - 0 1")
+        .unwrap();
+    assert!(engine.search(1).score > 0);
+    engine
+        .game
+        .try_set_from_fen("7k/8/8/8/p3K3/8/8/8 w - - 0 1")
+        .unwrap();
+    assert_eq!(engine.search(1).score, 0);
+
+    // A won ending is still worth less than the queen, so the engine promotes
+    engine
+        .game
+        .try_set_from_fen("8/1k2P3/8/8/8/8/8/4K3 w - - 1 2")
+        .unwrap();
+    let result = engine.search(4);
+    assert_eq!(result.best_move, Move::from_uci("e7e8q"));
+    assert!(result.score > PieceType::Queen.value());
+}
+
+#[test]
+fn test_play_and_fen() {
+    let mut game = Game::init();
+    let fen = game.play_and_fen(Move::from_uci("e2e4").unwrap());
+    assert_eq!(
+        fen.as_deref(),
+        Some("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+    );
+
+    // A rejected move leaves the game untouched
+    assert_eq!(game.play_and_fen(Move::from_uci("e4e6").unwrap()), None);
+    assert_eq!(game.play_and_fen(Move::from_uci("d2d4").unwrap()), None);
+    assert_eq!(game.get_fen(), fen.unwrap());
+
+    let fen = game.play_and_fen(Move::from_uci("g8f6").unwrap()).unwrap();
+    assert!(fen.ends_with(" w KQkq - 1 2"));
+}
+
+#[test]
+fn test_dead_position() {
+    let status_of = |fen: &str| game_from_fen(fen).status();
+
+    // A locked pawn chain with the kings stuck on their own side of it
+    let locked = "8/4k3/8/1p1p1p1p/1P1P1P1P/8/4K3/8 w - - 0 1";
+    assert_eq!(status_of(locked), GameStatus::DeadPosition);
+    assert!(GameStatus::DeadPosition.is_draw());
+    // The same chain with a gap on the a-file lets the white king through
+    let open_file = "8/4k3/8/3p1p1p/3P1P1P/8/4K3/8 w - - 0 1";
+    assert_eq!(status_of(open_file), GameStatus::Ongoing);
+    // A pawn that can still be pushed keeps the game alive
+    let free_pawn = "8/4k3/8/1p1p1p1p/1P1P1P1P/8/P3K3/8 w - - 0 1";
+    assert_eq!(status_of(free_pawn), GameStatus::Ongoing);
+    // So does any piece
+    let with_knight = "8/4k3/8/1p1p1p1p/1P1P1P1P/8/4K1N1/8 w - - 0 1";
+    assert_eq!(status_of(with_knight), GameStatus::Ongoing);
+}
+
+#[test]
+fn test_perft_parallel() {
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+        .unwrap();
+    let fen = game.get_fen();
+    for depth in 0..=3 {
+        assert_eq!(game.perft_parallel(depth), game.perft(depth));
+    }
+    assert_eq!(game.get_fen(), fen);
+}
+
+#[test]
+fn test_perft_parallel_depth_4() {
+    // Kiwipete has four million nodes at depth 4, the sparser perft position 3 has 43238
+    let game = game_from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+    assert_eq!(game.perft_parallel(4), 43238);
+}
+
+#[test]
+fn test_classify_move() {
+    let mut engine = Engine::init();
+    // Black's queen hangs to the rook
+    let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+    engine.game.try_set_from_fen(fen).unwrap();
+
+    let best_move = engine.get_best_move(1);
+    assert_eq!(best_move, Move::from_uci("d1d5").unwrap());
+    assert_eq!(engine.classify_move(&best_move, 1), MoveQuality::Best);
+    let king_move = Move::from_uci("e1f2").unwrap();
+    assert_eq!(engine.classify_move(&king_move, 1), MoveQuality::Blunder);
+    assert_eq!(engine.game.get_fen(), fen);
+}
+
+#[test]
+fn test_capture_promotion() {
+    let cases = [
+        (
+            "3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1",
+            "e7",
+            "d8",
+            "exd8=Q+",
+            "3Qk3/8/8/8/8/8/8/4K3 b - - 0 1",
+        ),
+        (
+            "4k3/8/8/8/8/8/4p3/3RK3 b - - 0 1",
+            "e2",
+            "d1",
+            "exd1=Q+",
+            "4k3/8/8/8/8/8/8/3qK3 w - - 0 2",
+        ),
+    ];
+    for (fen, source, target, san, fen_after) in cases {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        let white = game.white_turn;
+        let source = position_helper::try_letter_to_index(source).unwrap();
+        let target = position_helper::try_letter_to_index(target).unwrap();
+
+        let queen = PieceType::Queen.to_binary(white);
+        let promotion = game
+            .get_legal_moves(white)
+            .into_iter()
+            .find(|mv| mv.source == source && mv.target == target && mv.promotion == queen)
+            .unwrap();
+        assert!(promotion.is_capture());
+        assert!(promotion.is_promotion());
+        assert_eq!(game.move_to_san(promotion), san);
+        assert_eq!(game.san_to_move(san), Some(promotion));
+        let mut engine = Engine::init();
+        engine.game = game.clone();
+        assert_eq!(engine.get_san_from_move(promotion), san);
+
+        assert!(game.play_move_ob(promotion));
+        assert_eq!(game.board.state[target as usize], queen);
+        assert_eq!(game.board.state[source as usize], 0);
+        assert_eq!(game.get_fen(), fen_after);
+    }
+}
+
+#[test]
+fn test_search_depth_is_clamped() {
+    let mut engine = Engine::init();
+    // King and pawn endings are answered by the oracle, so even the deepest search is quick
+    engine
+        .game
+        .try_set_from_fen("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1")
+        .unwrap();
+    for depth in [MAX_SEARCH_DEPTH, MAX_SEARCH_DEPTH + 1, u8::MAX] {
+        let result = engine.search(depth);
+        assert_eq!(result.depth, MAX_SEARCH_DEPTH);
+        assert!(result.best_move.is_some());
+    }
+
+    engine.set_search_depth(u8::MAX);
+    assert_eq!(engine.search_depth(), MAX_SEARCH_DEPTH);
+}
+
+#[test]
+fn test_simple_fen_accepts_full_fen() {
+    let mut game = Game::init();
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 3 20";
+    assert!(game.set_from_simple_fen(fen.to_string()));
+    assert!(!game.white_turn);
+    assert_eq!(game.get_fen(), fen);
+
+    // A broken full FEN is refused and leaves the game alone
+    assert!(!game.set_from_simple_fen("r3k2r/8/8/8/8/8/8/R3K2R x Kq - 3 20".to_string()));
+    assert_eq!(game.get_fen(), fen);
+
+    // A bare piece placement still works as before
+    assert!(game.set_from_simple_fen("8/P7/8/8/8/8/8/8".to_string()));
+    assert_eq!(game.get_fen_simple(), "8/P7/8/8/8/8/8/8");
+}
+
+#[test]
+fn test_game_phase() {
+    let phase_of = |fen: &str| game_from_fen(fen).board.game_phase();
+
+    assert_eq!(Game::init().board.game_phase(), 24);
+    assert_eq!(phase_of("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), 0);
+    assert_eq!(phase_of("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"), 0);
+    // Queens traded: 24 - 2 * 4
+    let midgame = "r1b1kb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1B1KB1R w KQkq - 0 1";
+    assert_eq!(phase_of(midgame), 16);
+    // Promoted queens do not go past a full board
+    assert_eq!(phase_of("QQQQkQQQ/8/8/8/8/8/8/4K3 w - - 0 1"), 24);
+}
+
+#[test]
+fn test_safe_random_move() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut game = Game::init();
+    // The queen can take the pawn for free, but has squares where it would be lost
+    game.try_set_from_fen("4k3/8/8/3p4/8/1P1Q4/8/4K3 w - - 0 1")
+        .unwrap();
+    let hangs = |uci: &str| game.hangs_piece(&Move::from_uci(uci).unwrap());
+    assert!(!hangs("d3d5"));
+    assert!(!hangs("d3e2"));
+    // Defended by the pawn on b3, but a pawn is cheaper than the queen
+    assert!(hangs("d3c4"));
+    // Next to the king with nothing defending it
+    assert!(hangs("d3d7"));
+
+    let mut rng = StdRng::seed_from_u64(927);
+    let pure: Vec<Move> = (0..200)
+        .map(|_| game.random_move(&mut rng, false).unwrap())
+        .collect();
+    assert!(pure.iter().any(|mv| game.hangs_piece(mv)));
+    for _ in 0..200 {
+        let mv = game.safe_random_move(&mut rng, false).unwrap();
+        assert!(!game.hangs_piece(&mv), "{}", game.move_to_san(mv));
+    }
+}
+
+#[test]
+fn test_structured_errors() {
+    use std::error::Error;
+
+    assert_eq!(position_helper::try_letter_to_index("e4"), Ok(36));
+    assert_eq!(position_helper::try_letter_to_index("a8"), Ok(0));
+    for bad_square in ["", "e", "e9", "i4", "E4", "e44"] {
+        assert_eq!(
+            position_helper::try_letter_to_index(bad_square),
+            Err(SquareError::InvalidSquare(bad_square.to_string()))
+        );
+    }
+
+    assert!(matches!(
+        Move::try_from_uci("e2e9"),
+        Err(MoveError::InvalidNotation { .. })
+    ));
+    assert!(matches!(
+        Move::try_from_uci("e7e8k"),
+        Err(MoveError::InvalidPromotion { .. })
+    ));
+    assert_eq!(Move::try_from_uci("e2e4").ok(), Move::from_uci("e2e4"));
+
+    let mut game = Game::init();
+    let fen = game.get_fen();
+    let square_error = game.try_play_move("z9", "e4", "").unwrap_err();
+    assert_eq!(
+        square_error,
+        CherrisError::Square(SquareError::InvalidSquare("z9".to_string()))
+    );
+    let illegal = game.try_play_move("e2", "e5", "").unwrap_err();
+    assert!(matches!(
+        illegal,
+        CherrisError::Move(MoveError::IllegalMove { .. })
+    ));
+    assert!(illegal.source().is_some());
+    let promotion = game.try_play_move("e2", "e4", "x").unwrap_err();
+    assert!(matches!(
+        promotion,
+        CherrisError::Move(MoveError::InvalidPromotion { .. })
+    ));
+    assert_eq!(game.get_fen(), fen);
+
+    let fen_error = CherrisError::from(game.try_set_from_fen("rnbqkbnr/8 w - -").unwrap_err());
+    let bad_board = FenError::InvalidBoard("rnbqkbnr/8".to_string());
+    assert_eq!(fen_error, CherrisError::Fen(bad_board));
+    assert_eq!(game.get_fen(), fen);
+
+    // Bad placements are refused instead of panicking
+    assert!(!game.set_from_simple_fen("8/8/8/8/8/8/8/7X".to_string()));
+    assert!(!game.set_from_simple_fen("8/8/8/8/8/8/8/9".to_string()));
+
+    let after_e4 = game.try_play_move("e2", "e4", "").unwrap();
+    assert_eq!(after_e4, game.get_fen());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_entry_points() {
+    use std::panic::catch_unwind;
+
+    // They still agree with their try_ versions on good input
+    let fen = "r3k2r/8/8/8/8/8/1B6/R3K2R w KQkq - 0 1";
+    let mut game = Game::init();
+    game.set_from_fen(fen.to_string());
+    assert_eq!(game.get_fen(), game_from_fen(fen).get_fen());
+    assert_eq!(position_helper::letter_to_index("h8".to_string()), 7);
+    assert!(game.play_move_from_string("b2", "h8", ""));
+    assert_eq!(game.board.get_castling_fen(), "KQq");
+
+    // And panic, as documented, on bad input
+    assert!(catch_unwind(|| position_helper::letter_to_index("e".to_string())).is_err());
+    assert!(catch_unwind(|| Game::init().set_from_fen("not a fen".to_string())).is_err());
+    assert!(catch_unwind(|| Game::init().play_move_from_string("z", "e4", "")).is_err());
+}
+
+#[test]
+fn test_connected_passed_pawns() {
+    let mut engine = Engine::init();
+    let mut evaluate = |fen: &str| engine.evaluate(&game_from_fen(fen).board);
+
+    // Both pairs are passed on the fifth rank, and the a-, b- and c-file pawns share the
+    // same table values, so only the connection differs
+    let connected = evaluate("4k3/8/8/PP6/8/8/8/4K3 w - - 0 1");
+    let disconnected = evaluate("4k3/8/8/P1P5/8/8/8/4K3 w - - 0 1");
+    assert!(connected > disconnected);
+
+    // A blocked or guarded pawn is not passed
+    let passed = evaluate("4k3/8/8/P7/8/8/8/4K3 w - - 0 1");
+    let guarded = evaluate("4k3/1p6/8/P7/8/8/8/4K3 w - - 0 1");
+    assert!(passed - guarded > PieceType::Pawn.value());
+
+    // The same holds for black, with the board mirrored
+    let black_connected = evaluate("4k3/8/8/8/pp6/8/8/4K3 b - - 0 1");
+    assert_eq!(black_connected, -connected);
+}
+
+#[test]
+fn test_legal_reply_counts() {
+    let game = Game::init();
+    let moves = game.all_legal_moves();
+    assert_eq!(moves.len(), 20);
+    assert!(moves
+        .iter()
+        .all(|mv| game.legal_reply_count_after(mv) == 20));
+
+    // Perft divide at depth 2 of position 3 from the chess programming wiki
+    let mut game = Game::init();
+    game.try_set_from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1")
+        .unwrap();
+    let expected = [
+        ("e2e3", 15),
+        ("g2g3", 4),
+        ("a5a6", 15),
+        ("e2e4", 16),
+        ("g2g4", 17),
+        ("b4b1", 16),
+        ("b4b2", 16),
+        ("b4b3", 15),
+        ("b4a4", 15),
+        ("b4c4", 15),
+        ("b4d4", 15),
+        ("b4e4", 15),
+        ("b4f4", 2),
+        ("a5a4", 15),
+    ];
+    let moves = game.all_legal_moves();
+    assert_eq!(moves.len(), expected.len());
+    for (uci, replies) in expected {
+        let mv = Move::from_uci(uci).unwrap();
+        assert!(moves.contains(&mv), "{}", uci);
+        assert_eq!(game.legal_reply_count_after(&mv), replies, "{}", uci);
+    }
+    let total: usize = moves
+        .iter()
+        .map(|mv| game.legal_reply_count_after(mv))
+        .sum();
+    assert_eq!(total as u64, game.clone().perft(2));
+}
+
+#[test]
+fn test_fen_direct_import() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/8/8/8/8/8/6k1/4K2R b K - 12 40",
+    ];
+    for fen in fens {
+        let mut array = Game::init();
+        array.try_set_from_fen(fen).unwrap();
+        // Start from a different position so nothing is left over by accident
+        let mut direct = Game::init();
+        direct
+            .set_from_fen_direct("4k3/8/8/8/8/8/8/4K3 b - - 5 9")
+            .unwrap();
+        direct.set_from_fen_direct(fen).unwrap();
+
+        assert_eq!(direct.board.state, array.board.state, "{}", fen);
+        assert_eq!(direct.board.bitboard, array.board.bitboard, "{}", fen);
+        assert_eq!(direct.board.material, array.board.material, "{}", fen);
+        assert_eq!(direct.get_fen(), array.get_fen());
+        assert_eq!(direct.hash(), array.hash());
+    }
+
+    let mut board = Board::init();
+    assert!(!board.set_pieces_from_fen("rnbqkbnr/pppppppp/8/8"));
+    assert!(board.state.iter().all(|&piece| piece == 0));
+    assert!(board.bitboard.iter().all(|&bits| bits == 0));
+
+    // Bad records are rejected like in try_set_from_fen, even when the squares add up to 64
+    let mut game = Game::init();
+    let start = game.get_fen();
+    let uneven_ranks = "rnbqkbnr/ppppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert!(matches!(
+        game.set_from_fen_direct(uneven_ranks),
+        Err(FenError::InvalidBoard(_))
+    ));
+    assert_eq!(
+        game.set_from_fen_direct("8/8/8/8/8/8/8/8 w"),
+        Err(FenError::MissingField("castling"))
+    );
+    assert!(matches!(
+        game.set_from_fen_direct("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+        Err(FenError::InvalidMoveClock(_))
+    ));
+    assert_eq!(game.get_fen(), start);
+}
+
+#[test]
+fn test_root_scout_search() {
+    let fens = [
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+
+        let mut full_window = Engine::init_from_game(game.clone());
+        let scores = full_window.root_move_scores(1);
+        let full_window_nodes = full_window.nodes_searched();
+        let best_score = scores.iter().map(|&(_, score)| score).max().unwrap();
+        let first_best = scores.iter().find(|&&(_, score)| score == best_score);
+
+        let mut scout = Engine::init_from_game(game);
+        let result = scout.search(1);
+        assert_eq!(result.score, best_score, "{}", fen);
+        assert_eq!(result.best_move, first_best.map(|&(mv, _)| mv), "{}", fen);
+        assert!(result.nodes < full_window_nodes as u64, "{}", fen);
+        assert_eq!(scout.game.get_fen(), fen);
+    }
+}
+
+#[test]
+fn test_trapped_bishop() {
+    let mut engine = Engine::init();
+    let mut evaluate = |fen: &str| engine.evaluate(&game_from_fen(fen).board);
+    // Bxa7 won a pawn, but after ...b6 the bishop has nowhere to go
+    let trapped = evaluate("r1bqkbnr/B1p1pppp/1pn5/3p4/3P4/8/PPP1PPPP/RN1QKBNR w KQkq - 0 5");
+    assert!(trapped < 0, "{}", trapped);
+
+    // The same extra pawn with the bishop back on e3
+    let free = evaluate("r1bqkbnr/2p1pppp/1pn5/3p4/3P4/4B3/PPP1PPPP/RN1QKBNR w KQkq - 0 5");
+    assert!(free > 0, "{}", free);
+}
+
+#[test]
+fn test_search_info_line() {
+    let mut engine = Engine::init();
+    let result = engine.search(2);
+    let info = result.info_line();
+    let tokens: Vec<&str> = info.split_whitespace().collect();
+    assert_eq!(tokens[0], "info");
+    let field = |name: &str| {
+        let position = tokens.iter().position(|&token| token == name);
+        let value = tokens[position.unwrap_or_else(|| panic!("{} missing", name)) + 1];
+        value.parse::<u64>().unwrap()
+    };
+
+    assert_eq!(field("depth"), 2);
+    assert_eq!(field("nodes"), result.nodes);
+    // Nodes per second agree with the node count and the time taken
+    let nps = field("nps");
+    let expected = result.nodes as f64 / result.elapsed.as_secs_f64();
+    assert!(nps > 0 && (nps as f64 - expected).abs() <= expected * 0.01 + 1.0);
+    let hashfull = field("hashfull");
+    assert!(hashfull <= 1000);
+    assert_eq!(hashfull, engine.hashfull() as u64);
+
+    let pv = &tokens[tokens.iter().position(|&token| token == "pv").unwrap() + 1..];
+    let expected_pv: Vec<String> = result.pv.iter().map(Move::to_uci).collect();
+    assert_eq!(pv, expected_pv);
+    assert_eq!(Move::from_uci(pv[0]), result.best_move);
+    assert!(info.contains(&format!(" score cp {} ", result.score)));
+    assert_eq!(result.mate_in(), None);
+
+    // Mate scores count moves rather than centipawns
+    let mut engine = Engine::init();
+    engine.game = game_from_fen("7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+    let mating = engine.search(2);
+    assert_eq!(mating.mate_in(), Some(1));
+    assert!(mating.info_line().contains(" score mate 1 "));
+    // Black's only move, Kb8, runs into Rh8#
+    engine.game = game_from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1");
+    let mated = engine.search(2);
+    assert_eq!(mated.mate_in(), Some(-1));
+    assert!(mated.info_line().contains(" score mate -1 "));
+}
+
+#[test]
+fn test_validate_position() {
+    let validate = |fen: &str| game_from_fen(fen).validate_position();
+    assert_eq!(
+        validate("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+        Ok(())
+    );
+    assert_eq!(validate("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Ok(()));
+
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+        Err(PositionError::KingCount {
+            white: true,
+            count: 2
+        })
+    );
+    assert_eq!(
+        validate("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+        Err(PositionError::KingCount {
+            white: false,
+            count: 0
+        })
+    );
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+        Err(PositionError::PawnOnBackRank(56))
+    );
+    assert_eq!(
+        validate("p3k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+        Err(PositionError::PawnOnBackRank(0))
+    );
+    // White to move while the black king is attacked by the rook
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/4RK2 w - - 0 1"),
+        Err(PositionError::OpponentInCheck)
+    );
+    assert_eq!(
+        validate("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1"),
+        Err(PositionError::InvalidCastlingRight('K'))
+    );
+    assert_eq!(validate("r3k2r/8/8/8/8/8/8/R3K1R1 w Qkq - 0 1"), Ok(()));
+}
+
+#[test]
+fn test_mop_up_king_and_rook() {
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("8/1k6/8/8/8/8/8/R3K3 w - - 0 1")
+        .unwrap();
+    // King and rook mate a lone king within 16 moves from any position, but the mate lies far
+    // beyond a depth 2 search, which has to drive the lone king to the edge on its own
+    let mut white_moves = 0;
+    while white_moves < 16 && engine.game.status() == GameStatus::Ongoing {
+        if engine.game.white_turn {
+            white_moves += 1;
+        }
+        let mv = engine.search(2).best_move.unwrap();
+        assert!(engine.game.play_move_ob(mv));
+    }
+
+    let fen = engine.game.get_fen();
+    assert_eq!(engine.game.status(), GameStatus::Checkmate, "{}", fen);
+    let lone_king = engine.game.board.king_square(false).unwrap();
+    assert!(
+        [0, 7].contains(&(lone_king / 8)) || [0, 7].contains(&(lone_king % 8)),
+        "{}",
+        fen
+    );
+}
+
+#[test]
+fn test_capture_target() {
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k3/8/8/3pP3/8/8/8/R3K3 w - d6 0 2")
+        .unwrap();
+    // a8 is square 0 and e6 square 20
+    assert_eq!(game.board.piece_on(0), Some((PieceType::Rook, false)));
+    assert_eq!(game.board.piece_on(20), None);
+
+    // The rook on a8 is taken on its own square
+    let rook_capture = Move::from_uci("a1a8").unwrap();
+    assert_eq!(
+        game.capture_target(&rook_capture),
+        Some((PieceType::Rook, false))
+    );
+    // The en passant target d6 is empty, the pawn taken stands on d5
+    let en_passant = Move::from_uci("e5d6").unwrap();
+    assert_eq!(
+        game.capture_target(&en_passant),
+        Some((PieceType::Pawn, false))
+    );
+    assert_eq!(game.capture_target(&Move::from_uci("e5e6").unwrap()), None);
+    assert_eq!(game.capture_target(&Move::from_uci("e1e2").unwrap()), None);
+    // Nothing is played
+    assert_eq!(game.get_fen(), "r3k3/8/8/3pP3/8/8/8/R3K3 w - d6 0 2");
+}
+
+#[test]
+fn test_best_move_for_fen() {
+    let mut engine = Engine::init();
+    engine.game.try_play_move("e2", "e4", "").unwrap();
+    let live_fen = engine.game.get_fen();
+
+    // Back rank mate in a position unrelated to the live game
+    let result = engine
+        .best_move_for_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1)
+        .unwrap();
+    assert_eq!(result.best_move, Move::from_uci("a1a8"));
+    assert_eq!(engine.game.get_fen(), live_fen);
+    assert_eq!(engine.game.history().len(), 1);
+
+    assert!(matches!(
+        engine.best_move_for_fen("not a fen", 1),
+        Err(FenError::MissingField(_))
+    ));
+    assert_eq!(engine.game.get_fen(), live_fen);
+}
+
+#[test]
+fn test_hash_move_ordering() {
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    let mut repeated_nodes = vec![];
+    for ordering in [false, true] {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        engine.set_hash_move_ordering(ordering);
+        let first = engine.search(2);
+        // The second search finds the best moves of the first one already stored
+        let second = engine.search(2);
+        assert_eq!(second.score, first.score);
+        repeated_nodes.push(second.nodes);
+    }
+    assert!(
+        repeated_nodes[1] * 4 < repeated_nodes[0],
+        "{:?}",
+        repeated_nodes
+    );
+}
+
+#[test]
+fn test_board_diff() {
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")
+        .unwrap();
+    let before = game.board.clone();
+    assert!(before.diff(&game.board).is_empty());
+
+    // Castling kingside moves the king from e1 to g1 and the rook from h1 to f1
+    assert!(game.try_play_move("e1", "g1", "").is_ok());
+    assert_eq!(
+        before.diff(&game.board),
+        vec![
+            (60, Some((PieceType::King, true)), None),
+            (61, None, Some((PieceType::Rook, true))),
+            (62, None, Some((PieceType::King, true))),
+            (63, Some((PieceType::Rook, true)), None),
+        ]
+    );
+
+    // En passant empties the captured pawn's square as well as the source
+    game.undo_move();
+    assert!(game.try_play_move("e5", "d6", "").is_ok());
+    assert_eq!(
+        before.diff(&game.board),
+        vec![
+            (19, None, Some((PieceType::Pawn, true))),
+            (27, Some((PieceType::Pawn, false)), None),
+            (28, Some((PieceType::Pawn, true)), None),
+        ]
+    );
+}
+
+#[test]
+fn test_quiet_search() {
+    // The engine searches a reply, and its output carries only what the client prints
+    let output = run_play_cli(&["--depth", "1"], "e4\nquit\n");
+    assert!(output.contains("Engine plays"), "{}", output);
+    assert!(!output.contains("Best move"), "{}", output);
+    assert!(!output.contains("positions with"), "{}", output);
+
+    // Asking for it adds the search summary
+    let output = run_play_cli(&["--depth", "1", "--verbose"], "e4\nquit\n");
+    assert!(output.contains("Engine plays"), "{}", output);
+    assert!(output.contains("Best move"), "{}", output);
+    assert!(output.contains("positions with"), "{}", output);
+}
+
+#[test]
+fn test_pawn_structure_cache() {
+    let board = |fen: &str| game_from_fen(fen).board;
+    // The same pawns, with the pieces and rights around them changed
+    let first = board("4k3/p7/8/1P6/3p4/8/P7/4K3 w - - 0 1");
+    let second = board("r2qk3/p7/3n4/1P6/3p4/5B2/P7/R3K2R b KQ - 3 20");
+    let other_pawns = board("4k3/p7/8/1P6/8/8/P7/4K3 w - - 0 1");
+    assert_eq!(first.pawn_hash(), second.pawn_hash());
+    assert_ne!(first.pawn_hash(), other_pawns.pawn_hash());
+
+    let mut engine = Engine::init();
+    let score = engine.pawn_structure(&first);
+    assert_eq!(engine.pawn_structure(&second), score);
+    assert_eq!(engine.pawn_cache_len(), 1);
+    // The cached score is the one a fresh engine works out for the other position
+    assert_eq!(Engine::init().pawn_structure(&second), score);
+    engine.pawn_structure(&other_pawns);
+    assert_eq!(engine.pawn_cache_len(), 2);
+
+    // The full evaluation goes through the pawn cache too
+    let mut fresh = Engine::init();
+    fresh.evaluate(&second);
+    assert_eq!(fresh.pawn_cache_len(), 1);
+}
+
+#[test]
+fn test_fen_whitespace() {
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2";
+    let padded = "  rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR  w\tKQkq   e6  0 2 \n";
+
+    let mut game = Game::init();
+    game.try_set_from_fen(padded).unwrap();
+    assert_eq!(game.get_fen(), fen);
+
+    let mut game = Game::init();
+    assert_eq!(game.try_set_from_fen(padded), Ok(()));
+    assert_eq!(game.get_fen(), fen);
+
+    let mut game = Game::init();
+    assert!(game.set_from_simple_fen(padded.to_string()));
+    assert_eq!(game.get_fen(), fen);
+
+    // An en passant square in capitals is read like a lowercase one
+    let mut game = Game::init();
+    let capitals = fen.replace("e6", "E6");
+    assert_eq!(game.try_set_from_fen(&capitals), Ok(()));
+    assert_eq!(game.get_fen(), fen);
+}
+
+#[test]
+fn test_evaluate_relative() {
+    // White is a knight up, and only the side to move differs
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("4k3/pppp4/8/8/8/8/PPPP4/1N2K3 w - - 0 1")
+        .unwrap();
+    let white_view = engine.evaluate(&engine.game.board.clone());
+    assert!(white_view > 0);
+    assert_eq!(engine.evaluate_relative(), white_view);
+
+    engine
+        .game
+        .try_set_from_fen("4k3/pppp4/8/8/8/8/PPPP4/1N2K3 b - - 0 1")
+        .unwrap();
+    assert_eq!(engine.evaluate(&engine.game.board.clone()), white_view);
+    assert_eq!(engine.evaluate_relative(), -white_view);
+}
+
+#[test]
+fn test_avoids_stalemate_when_winning() {
+    // Qb6 takes every square from the cornered king without giving check
+    let mut engine = Engine::init();
+    engine
+        .game
+        .try_set_from_fen("k2K4/8/4Q3/8/8/8/8/8 w - - 0 1")
+        .unwrap();
+    let mv = engine.get_best_move(1);
+    assert_ne!(mv, Move::from_uci("e6b6").unwrap());
+
+    // The engine goes on to mate instead
+    for _ in 0..8 {
+        let Some(mv) = engine.search(2).best_move else {
+            break;
+        };
+        assert!(engine.game.play_move_ob(mv));
+        assert_ne!(engine.game.status(), GameStatus::Stalemate);
+    }
+    assert_eq!(engine.game.status(), GameStatus::Checkmate);
+}
+
+#[test]
+fn test_move_constructors() {
+    // A black pawn on g2 promoting to a knight on h1
+    let promotion = Move::promotion(54, 63, PieceType::Knight, false);
+    assert_eq!(promotion.promotion, PIECE_BIT | KNIGHT);
+    assert!(promotion.is_promotion());
+    assert_eq!(promotion, Move::from_uci("g2h1n").unwrap());
+    let white_queen = Move::promotion(12, 4, PieceType::Queen, true);
+    assert_eq!(white_queen.promotion, PIECE_BIT | WHITE_BIT | QUEEN);
+
+    let mut game = Game::init();
+    game.try_set_from_fen("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1")
+        .unwrap();
+    let legal_moves = game.get_legal_moves(game.white_turn);
+    for mv in [
+        Move::castle_kingside(false),
+        Move::castle_queenside(false),
+        Move::en_passant(35, 44),
+        Move::quiet(0, 8),
+    ] {
+        let generated = legal_moves.iter().find(|&&legal| legal == mv).unwrap();
+        assert_eq!(generated.flags, mv.flags, "{}", mv.to_uci());
+    }
+    assert_eq!(Move::castle_kingside(true).to_uci(), "e1g1");
+    assert_eq!(Move::castle_queenside(true).to_uci(), "e1c1");
+}
+
+#[test]
+fn test_attackers_of() {
+    let mut game = Game::init();
+    // d5 (square 27) is hit by the pawns on c4 and e4, the knight on f6, the rook on d1
+    // and the queen on a5; the bishop on b3 is blocked by the pawn on c4
+    game.try_set_from_fen("4k3/8/5N2/Q2p4/2P1P3/1B6/8/3RK3 w - - 0 1")
+        .unwrap();
+    let expected = ["e4", "f6", "d1", "a5", "c4"]
+        .iter()
+        .map(|square| position_helper::try_letter_to_index(square).unwrap())
+        .fold(0u64, |bits, square| bits | 1 << (square ^ 56));
+    assert_eq!(game.board.attackers_of(27, true), expected);
+    assert_eq!(game.board.attackers_of(27, false), 0);
+    // The layout matches the piece bitboards: the white pawns among the attackers
+    assert_eq!(
+        (game.board.attackers_of(27, true) & game.board.bitboard[0]).count_ones(),
+        2
+    );
+    assert!(game.is_attacked(27, true));
+    assert!(!game.is_attacked(27, false));
+}
+
+#[test]
+fn test_play_engine_game() {
+    // Weakened engines pick among close moves at random, so only the seed fixes the game.
+    // A small endgame keeps the searches short.
+    let play = || {
+        let engine = || EngineBuilder::new().skill_level(5).seed(11).build();
+        let (mut white, mut black) = (engine(), engine());
+        white
+            .game
+            .try_set_from_fen("4k3/pp6/8/8/8/8/6PP/4K3 w - - 0 1")
+            .unwrap();
+        let status = engine::play_engine_game(&mut white, &mut black, 6, 1);
+        assert_eq!(white.game.get_fen(), black.game.get_fen());
+        (status, white.game.history().to_vec())
+    };
+    let (status, history) = play();
+    assert_eq!(status, GameStatus::MoveLimit);
+    assert!(status.is_draw());
+    assert_eq!(history.len(), 12);
+    assert_eq!(play(), (status, history));
+
+    // A game that ends by the rules reports how it ended, even with no move limit to speak of
+    let mut white = EngineBuilder::new().seed(3).build();
+    white
+        .game
+        .try_set_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")
+        .unwrap();
+    let mut black = EngineBuilder::new().seed(3).build();
+    let status = engine::play_engine_game(&mut white, &mut black, u32::MAX, 1);
+    assert_eq!(status, GameStatus::Checkmate);
+    assert_eq!(black.game.history().len(), 1);
+}
+
+#[test]
+fn test_is_quiet() {
+    // Calm positions: nothing is attacked, or every capture loses material
+    assert!(Game::init().is_quiet());
+    let italian =
+        game_from_fen("r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+    assert!(italian.is_quiet());
+
+    // In check
+    let check = game_from_fen("rnbqkbnr/ppp2ppp/3p4/1B2p3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 3");
+    assert!(!check.is_quiet());
+
+    // The knight on e5 is defended by the d6 pawn, so taking it with the rook loses the
+    // exchange, while the rook on a5 hangs
+    let game = game_from_fen("4k3/8/3p4/r3n3/8/8/8/R3RK2 w - - 0 1");
+    assert_eq!(game.see(&Move::from_uci("e1e5").unwrap()), 300 - 500);
+    assert_eq!(game.see(&Move::from_uci("a1a5").unwrap()), 500);
+    assert_eq!(game.see(&Move::from_uci("a1a2").unwrap()), 0);
+    assert!(!game.is_quiet());
+    let without_rook = game_from_fen("4k3/8/3p4/4n3/8/8/8/R3RK2 w - - 0 1");
+    assert!(without_rook.is_quiet());
+}
+
+#[test]
+fn test_load_pgn() {
+    let pgn = r#"[Event "Casual game"]
+[White "A"]
+[Black "B"]
+
+1. e4 e5 2. Nf3 {the usual} Nc6 (2... d6 3. d4) 3.Bb5 a6 $1 4. Ba4 Nf6
+5. O-O Be7 ; closed Ruy Lopez
+1/2-1/2"#;
+    let mut game = Game::init();
+    game.load_pgn(pgn).unwrap();
+    assert_eq!(
+        game.fen(),
+        "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6"
+    );
+    assert_eq!(game.history().len(), 10);
+
+    // Play on from the loaded game, then take everything back to the start
+    assert!(game.try_play_move("f1", "e1", "").is_ok());
+    assert_eq!(game.undo_moves(20), 11);
+    assert_eq!(game.fen(), Game::init().fen());
+
+    // A FEN tag sets the first position, and an illegal move leaves the game as it was
+    let from_fen = Game::from_pgn("[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n\n1. O-O-O Kf7 *");
+    let from_fen = from_fen.unwrap();
+    assert_eq!(from_fen.fen(), "8/5k2/8/8/8/8/8/2KR4 w - - 2 2");
+    assert_eq!(from_fen.history()[0], "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+    let error = game.load_pgn("1. e4 e5 2. Ke3").unwrap_err();
+    assert_eq!(
+        error,
+        CherrisError::Move(MoveError::IllegalMove {
+            index: 2,
+            notation: "Ke3".to_string()
+        })
+    );
+    assert_eq!(game.fen(), Game::init().fen());
+}
+
+#[test]
+fn test_piece_at() {
+    let mut game = Game::init();
+    let piece = game.board.piece_at(4).unwrap();
+    assert_eq!(piece.class, PieceType::King);
+    assert!(!piece.is_white);
+    assert!(game.board.piece_at(27).is_none());
+    assert!(game.board.piece_at(64).is_none());
+    assert_eq!(game.get_piece_at_square("d5".to_string()), "None");
+
+    // Bytes without the piece bit or with unknown type bits are not pieces
+    let corrupted_bytes = [
+        WHITE_BIT | ROOK,
+        PIECE_BIT | 9,
+        PIECE_BIT | 15,
+        PIECE_BIT | 32 | PAWN_BIT,
+    ];
+    for corrupted in corrupted_bytes {
+        game.board.state[27] = corrupted;
+        assert!(game.board.piece_at(27).is_none(), "{}", corrupted);
+        assert_eq!(game.board.piece_on(27), None);
+        assert_eq!(game.get_piece_at_square("d5".to_string()), "None");
+    }
+}
+
+#[test]
+fn test_blunder_check() {
+    // Black may claim a draw by the fifty-move rule after any white move, so the search
+    // scores every move alike even though the knight attacks the queen
+    let fen = "7k/8/8/8/8/2n5/8/R2QK3 w - - 100 80";
+    let mut engine = EngineBuilder::new().build();
+    engine.game.try_set_from_fen(fen).unwrap();
+    let scores = engine.root_move_scores(1);
+    assert!(scores.iter().all(|&(_, score)| score == 0));
+    assert!(scores
+        .iter()
+        .any(|(mv, _)| engine.material_hung_by(mv) >= engine::BLUNDER_MARGIN));
+
+    let hung_by = |uci: &str| engine.material_hung_by(&Move::from_uci(uci).unwrap());
+    // Qd5 walks into the knight, Ra2 leaves the queen to be traded for it
+    assert_eq!(hung_by("d1d5"), PieceType::Queen.value());
+    assert_eq!(
+        hung_by("a1a2"),
+        PieceType::Queen.value() - PieceType::Knight.value()
+    );
+    assert_eq!(hung_by("d1d2"), 0);
+    // A move that cannot be played hangs nothing
+    assert_eq!(hung_by("d1h1"), 0);
+
+    // The blunder check picks one of the tied moves that keeps the material
+    let mut engine = EngineBuilder::new().blunder_check(true).build();
+    engine.game.try_set_from_fen(fen).unwrap();
+    let result = engine.search(1);
+    assert_eq!(result.score, 0);
+    let mv = result.best_move.unwrap();
+    assert!(engine.material_hung_by(&mv) < engine::BLUNDER_MARGIN);
+}
+
+#[test]
+fn test_evaluate_verbose() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        // Knight outpost on d5, passed pawn on a5 and a rook on the seventh
+        "6k1/1R3ppp/8/P2N4/4P3/8/5PPP/6K1 w - - 0 1",
+        // Bare king against king and rook
+        "8/8/8/3k4/8/8/8/R3K3 w - - 0 1",
+        // White's back rank is boxed in against black's heavy pieces
+        "r2q1rk1/pp3ppp/2n1bn2/8/8/2N1BN2/PP3PPP/R2Q1RK1 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        let mut engine = Engine::init();
+        let breakdown = engine.evaluate_verbose(&game.board);
+        assert_eq!(breakdown.total(), engine.evaluate(&game.board), "{}", fen);
+    }
+
+    let mut game = Game::init();
+    game.try_set_from_fen(fens[1]).unwrap();
+    let breakdown = Engine::init().evaluate_verbose(&game.board);
+    let material =
+        PieceType::Rook.value() + PieceType::Knight.value() + 2 * PieceType::Pawn.value();
+    assert_eq!(breakdown.material, material);
+    assert!(breakdown.knights > 0);
+    assert!(breakdown.pawn_structure > 0);
+    assert!(breakdown.seventh_rank > 0);
+
+    // The terminal client prints it on demand
+    let output = run_play_cli(&[], "eval\nquit\n");
+    assert!(output.contains("material"), "{}", output);
+    assert!(output.contains("total"), "{}", output);
+}
+
+#[test]
+fn test_stale_en_passant_square() {
+    let en_passant_moves = |fen: &str| -> Vec<Move> {
+        let game = game_from_fen(fen);
+        game.get_all_moves_for_color(game.white_turn)
+            .into_iter()
+            .filter(|mv| mv.is_en_passant())
+            .collect()
+    };
+
+    // d5 attacks c6, but there is no black pawn on c5 to take
+    assert!(en_passant_moves("4k3/8/8/3P4/8/8/8/4K3 w - c6 0 1").is_empty());
+    // The square belongs to the side to move's own double push
+    assert!(en_passant_moves("4k3/8/8/8/8/8/3P4/4K3 w - e3 0 1").is_empty());
+    assert!(en_passant_moves("4k3/8/8/8/3p4/8/8/4K3 b - e6 0 1").is_empty());
+
+    // With the pawn that just pushed in place the capture is offered as before
+    assert_eq!(
+        en_passant_moves("4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 1"),
+        vec![Move::en_passant(27, 18)]
+    );
+    let mut game = Game::init();
+    game.try_set_from_fen("4k3/8/8/3P4/8/8/8/4K3 w - c6 0 1")
+        .unwrap();
+    assert!(!game.is_legal(&Move::en_passant(27, 18)));
+}
+
+#[test]
+fn test_color() {
+    assert_eq!(Color::White.opposite(), Color::Black);
+    assert_eq!(Color::Black.opposite(), Color::White);
+    assert_eq!(Color::from(true), Color::White);
+    assert!(!Color::Black.is_white());
+
+    let mut game = Game::init();
+    assert_eq!(game.turn(), Color::White);
+    assert!(game.try_play_move("e2", "e4", "").is_ok());
+    assert_eq!(game.turn(), Color::Black);
+
+    // The Color and bool forms give the same moves
+    assert_eq!(
+        game.legal_moves_for(game.turn()),
+        game.get_legal_moves(false)
+    );
+    assert_eq!(
+        game.all_moves_for(Color::White),
+        game.get_all_moves_for_color(true)
+    );
+    assert_eq!(game.legal_moves_for(Color::Black).len(), 20);
+    let white_moves = game.all_moves_for(game.turn().opposite());
+    assert!(white_moves.contains(&Move::from_uci("d1h5").unwrap()));
+
+    // ChessGame keeps its bool signatures, so it can still be used as a trait object
+    let dyn_game: &dyn ChessGame = &game;
+    assert_eq!(dyn_game.get_legal_moves(false).len(), 20);
+}
+
+#[test]
+fn test_position_key_repetition() {
+    let start = Game::init().get_fen();
+    let mut game = Game::init();
+    game.set_from_moves(None, &["g1f3", "g8f6", "f3g1", "f6g8"])
+        .unwrap();
+
+    // The knights are home again, with only the move counters telling the FENs apart
+    let current = game.get_fen();
+    assert_ne!(current, start);
+    assert_eq!(Game::position_key(&current), Game::position_key(&start));
+    assert_eq!(
+        Game::position_key(&start),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"
+    );
+    assert_eq!(game.repetition_count(), 2);
+
+    // Undoing still restores the exact counters of each position
+    let history = game.history().to_vec();
+    assert!(game.undo_move());
+    assert_eq!(game.get_fen(), history[3]);
+    assert_eq!(game.half_move_clock(), 3);
+    assert_eq!(game.undo_moves(3), 3);
+    assert_eq!(game.get_fen(), start);
+    assert_eq!(game.repetition_count(), 1);
+}
+
+#[test]
+fn test_quiet_moves() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        // Kiwipete: castling both ways, captures for most pieces
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        // En passant, and promotions by a push and by a capture
+        "1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        "r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1",
+    ];
+    for fen in fens {
+        let mut game = Game::init();
+        game.try_set_from_fen(fen).unwrap();
+        let quiet_moves = game.get_quiet_moves();
+        let capture_moves = game.get_capture_moves();
+        let all_moves = game.all_moves_for(game.turn());
+        let uci_set = |moves: &[Move]| moves.iter().map(Move::to_uci).collect::<HashSet<_>>();
+        let (quiets, captures) = (uci_set(&quiet_moves), uci_set(&capture_moves));
+
+        assert!(quiets.is_disjoint(&captures), "{}", fen);
+        assert_eq!(&quiets | &captures, uci_set(&all_moves), "{}", fen);
+        let total = quiet_moves.len() + capture_moves.len();
+        assert_eq!(total, all_moves.len(), "{}", fen);
+        let onto_empty_squares = quiet_moves.iter().all(|mv| game.board.is_empty(mv.target));
+        assert!(onto_empty_squares, "{}", fen);
+        assert!(!quiet_moves.iter().any(Move::is_en_passant), "{}", fen);
+    }
+
+    let mut game = Game::init();
+    game.try_set_from_fen(fens[2]).unwrap();
+    let quiets = game.get_quiet_moves();
+    assert!(quiets.contains(&Move::from_uci("a7a8q").unwrap()));
+    let captures = game.get_capture_moves();
+    assert!(captures.contains(&Move::from_uci("a7b8q").unwrap()));
+    assert!(captures.contains(&Move::en_passant(28, 19)));
+
+    game.try_set_from_fen(fens[1]).unwrap();
+    let quiets = game.get_quiet_moves();
+    assert!(quiets.contains(&Move::castle_kingside(true)));
+}
+
+#[test]
+fn test_futility_pruning() {
+    let fens_and_moves = [
+        // The fork wins the rook back: the king has to answer the check first, and taking
+        // the rook is a capture that SEE says is worth searching
+        ("4k3/8/8/8/3n4/8/8/R3K2R b - - 0 1", "d4c2"),
+        ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", "d1d8"),
+    ];
+    for (fen, best_move) in fens_and_moves {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        let mv = engine.search(2).best_move;
+        assert_eq!(mv, Move::from_uci(best_move), "{}", fen);
+    }
+
+    // A quiet position takes fewer nodes to search with the pruning on
+    let fen = "6k1/pp1n1ppp/2p5/8/8/2P2N2/PP3PPP/6K1 w - - 0 1";
+    let mut results = vec![];
+    for pruning in [false, true] {
+        let mut engine = Engine::init();
+        engine.game.try_set_from_fen(fen).unwrap();
+        engine.set_futility_pruning(pruning);
+        results.push(engine.search(2));
+    }
+    assert_eq!(results[1].best_move, results[0].best_move);
+    let (unpruned, pruned) = (results[0].nodes, results[1].nodes);
+    assert!(pruned < unpruned, "{} {}", pruned, unpruned);
+}